@@ -0,0 +1,69 @@
+/* Block character glyphs for drawing the timer large */
+
+const SMALL: &'static [(char, [&'static str; 3])] = &[
+    ('0', ["┏━┓", "┃ ┃", "┗━┛"]),
+    ('1', ["╺┓ ", " ┃ ", "╺┻╸"]),
+    ('2', ["╺━┓", "┏━┛", "┗━╸"]),
+    ('3', ["╺━┓", " ━┫", "╺━┛"]),
+    ('4', ["╻ ╻", "┗━┫", "  ╹"]),
+    ('5', ["┏━╸", "┗━┓", "╺━┛"]),
+    ('6', ["┏━╸", "┣━┓", "┗━┛"]),
+    ('7', ["╺━┓", "  ┃", "  ╹"]),
+    ('8', ["┏━┓", "┣━┫", "┗━┛"]),
+    ('9', ["┏━┓", "┗━┫", "╺━┛"]),
+    (':', [" ", "╏", " "]),
+    ('.', [" ", " ", "╻"]),
+    ('-', ["   ", "╺━╸", "   "]),
+    (' ', [" ", " ", " "]),
+];
+
+const LARGE: &'static [(char, [&'static str; 5])] = &[
+    ('0', ["███", "█ █", "█ █", "█ █", "███"]),
+    ('1', [" █ ", "██ ", " █ ", " █ ", "███"]),
+    ('2', ["███", "  █", "███", "█  ", "███"]),
+    ('3', ["███", "  █", "███", "  █", "███"]),
+    ('4', ["█ █", "█ █", "███", "  █", "  █"]),
+    ('5', ["███", "█  ", "███", "  █", "███"]),
+    ('6', ["███", "█  ", "███", "█ █", "███"]),
+    ('7', ["███", "  █", "  █", "  █", "  █"]),
+    ('8', ["███", "█ █", "███", "█ █", "███"]),
+    ('9', ["███", "█ █", "███", "  █", "███"]),
+    (':', [" ", "█", " ", "█", " "]),
+    ('.', [" ", " ", " ", " ", "█"]),
+    ('-', ["   ", "   ", "███", "   ", "   "]),
+    (' ', [" ", " ", " ", " ", " "]),
+];
+
+/* Height of the tallest font that fits in `rows`, if any does */
+pub fn font_height(rows: u16) -> Option<u16> {
+    if rows >= 5 {
+        Some(5)
+    } else if rows >= 3 {
+        Some(3)
+    } else {
+        None
+    }
+}
+
+/* Lines of `text` drawn in the font of the given height, or None if it has
+ * a character we have no glyph for */
+pub fn render(text: &str, height: u16) -> Option<Vec<String>> {
+    let mut lines = vec![String::new(); height as usize];
+
+    for (i, c) in text.chars().enumerate() {
+        let glyph: Vec<&str> = match height {
+            3 => SMALL.iter().find(|&&(g, _)| g == c)?.1.to_vec(),
+            5 => LARGE.iter().find(|&&(g, _)| g == c)?.1.to_vec(),
+            _ => return None,
+        };
+
+        for (line, row) in lines.iter_mut().zip(glyph) {
+            if i > 0 {
+                line.push(' ');
+            }
+            line.push_str(row);
+        }
+    }
+
+    Some(lines)
+}
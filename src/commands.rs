@@ -0,0 +1,81 @@
+use export;
+use livesplit_core::{Run, Segment};
+use run_file::{self, RunFile};
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::Path;
+
+/* Write a fresh splits file. Segment names come from a comma separated list,
+ * or one per line on stdin. */
+pub fn new_run(output: &str, game: &str, category: &str, segments: Option<&str>, force: bool)
+               -> Result<(), String> {
+    if !force && Path::new(output).exists() {
+        return Err(format!("{} already exists, use --force to overwrite it", output));
+    }
+
+    let names: Vec<String> = match segments {
+        Some(segments) => segments.split(',').map(|s| s.trim().to_string()).collect(),
+        None => {
+            let stdin = io::stdin();
+            let lines = stdin.lock()
+                .lines()
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Unable to read segment names: {}", e))?;
+            lines.into_iter().map(|s| s.trim().to_string()).collect()
+        }
+    };
+    let names: Vec<String> = names.into_iter().filter(|s| !s.is_empty()).collect();
+    if names.is_empty() {
+        return Err(String::from("A run needs at least one segment"));
+    }
+
+    let mut run = Run::new();
+    run.set_game_name(game);
+    run.set_category_name(category);
+    for name in names {
+        run.push_segment(Segment::new(name));
+    }
+
+    RunFile::new(Some(output.to_string()), 0).save(&run)
+}
+
+/* Read splits in any format livesplit-core can parse and write them as
+ * LiveSplit .lss, Urn JSON or a CSV of segment times */
+pub fn convert(input: &str, output: &str, format: Option<&str>) -> Result<(), String> {
+    let format = match format {
+        Some(format) => format.to_lowercase(),
+        None => {
+            match Path::new(output).extension().and_then(|e| e.to_str()) {
+                Some("json") => String::from("urn"),
+                Some("csv") => String::from("csv"),
+                _ => String::from("lss"),
+            }
+        }
+    };
+
+    let run = run_file::load(input)?;
+
+    /* Like the .lss, written atomically so a failed write never leaves a truncated file */
+    let path = Path::new(output);
+    let written = match format.as_str() {
+        "lss" => return RunFile::new(Some(output.to_string()), 0).save(&run),
+        "urn" => run_file::write_atomically(path, |writer| export::write_urn(&run, writer)),
+        "csv" => run_file::write_atomically(path, |writer| export::write_csv(&run, writer)),
+        _ => return Err(format!("Unknown format {}, expected lss, urn or csv", format)),
+    };
+    written.map_err(|e| format!("Unable to write {}: {}", output, e))
+}
+
+/* Dump attempts, segment times, golds and the sum of best to stdout, as CSV
+ * or JSON */
+pub fn stats(input: &str, format: &str) -> Result<(), String> {
+    let run = run_file::load(input)?;
+
+    let stdout = io::stdout();
+    let mut writer = BufWriter::new(stdout.lock());
+    let written = match format.to_lowercase().as_str() {
+        "csv" => export::write_stats_csv(&run, &mut writer),
+        "json" => export::write_stats_json(&run, &mut writer).and_then(|_| write!(writer, "\n")),
+        _ => return Err(format!("Unknown format {}, expected csv or json", format)),
+    };
+    written.and_then(|_| writer.flush()).map_err(|e| format!("Unable to write stats: {}", e))
+}
@@ -0,0 +1,100 @@
+use serde_json;
+use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use layout::{SplitsSettings, TimerSettings};
+use toml;
+
+/* Settings read from the config file. Every section is optional. */
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Config {
+    /* Action name to the keys that trigger it */
+    pub keys: HashMap<String, Vec<String>>,
+    pub reset: ResetConfig,
+    /* Splits window used when the layout doesn't say otherwise */
+    pub splits: SplitsSettings,
+    /* Timer accuracy and rows used when the layout doesn't say otherwise */
+    pub timer: TimerSettings,
+}
+
+/* How a reset has to be confirmed */
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct ResetConfig {
+    pub confirm: ResetConfirm,
+    /* Window for the second press when confirm = "double-press" */
+    pub double_press_ms: u64,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResetConfirm {
+    /* Ask whether to update the splits */
+    Prompt,
+    /* Press reset twice in quick succession */
+    DoublePress,
+    /* Reset immediately */
+    None,
+}
+
+impl Default for ResetConfig {
+    fn default() -> ResetConfig {
+        ResetConfig {
+            confirm: ResetConfirm::Prompt,
+            double_press_ms: 500,
+        }
+    }
+}
+
+/* Just the key bindings, as read from a --keymap file */
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+struct KeymapFile {
+    keys: HashMap<String, Vec<String>>,
+}
+
+impl Config {
+    /* Load the config file if there is one. A missing file is not an error. */
+    pub fn load() -> Result<Config, String> {
+        match default_path() {
+            Some(ref path) if path.exists() => parse_file(path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    /* Apply the bindings from a separate keymap file on top of the config */
+    pub fn merge_keymap(&mut self, path: &Path) -> Result<(), String> {
+        let keymap: KeymapFile = parse_file(path)?;
+        self.keys.extend(keymap.keys);
+        Ok(())
+    }
+}
+
+/* $XDG_CONFIG_HOME/livesplit-one-terminal/config.toml, or the same under ~/.config */
+fn default_path() -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("livesplit-one-terminal").join("config.toml"))
+}
+
+/* Files ending in .json are read as JSON, everything else as TOML */
+fn parse_file<T>(path: &Path) -> Result<T, String>
+    where T: ::serde::de::DeserializeOwned
+{
+    let mut contents = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .map_err(|e| format!("Unable to open {}: {}", path.display(), e))?;
+
+    let is_json = path.extension().map(|ext| ext == "json").unwrap_or(false);
+    if is_json {
+        serde_json::from_str(&contents).map_err(|e| format!("Unable to parse {}: {}", path.display(), e))
+    } else {
+        toml::from_str(&contents).map_err(|e| format!("Unable to parse {}: {}", path.display(), e))
+    }
+}
@@ -0,0 +1,123 @@
+use livesplit_core::component::graph;
+use std::cmp::{min, max};
+
+/* What a cell of the graph shows, which decides its color. Where several
+ * things meet in one cell, the later kind wins. */
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub enum Ink {
+    Empty,
+    Comparison,
+    Line,
+    Ahead,
+    Behind,
+    Gold,
+}
+
+/* Braille characters give every cell two dots across and four down */
+const DOTS_ACROSS: usize = 2;
+const DOTS_DOWN: usize = 4;
+const BRAILLE_BLANK: u32 = 0x2800;
+/* Bit of each dot, by column then row */
+const DOT_BITS: [[u8; DOTS_DOWN]; DOTS_ACROSS] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    dots: Vec<u8>,
+    inks: Vec<Ink>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Canvas {
+        Canvas {
+            width: width,
+            height: height,
+            dots: vec![0; width * height],
+            inks: vec![Ink::Empty; width * height],
+        }
+    }
+
+    /* Set a dot, counted from the top left */
+    fn dot(&mut self, x: usize, y: usize, ink: Ink) {
+        let (column, row) = (x / DOTS_ACROSS, y / DOTS_DOWN);
+        if column >= self.width || row >= self.height {
+            return;
+        }
+        let cell = row * self.width + column;
+        self.dots[cell] |= DOT_BITS[x % DOTS_ACROSS][y % DOTS_DOWN];
+        if ink > self.inks[cell] {
+            self.inks[cell] = ink;
+        }
+    }
+
+    fn line(&mut self, from: (usize, usize), to: (usize, usize), ink: Ink) {
+        let (x0, y0) = (from.0 as isize, from.1 as isize);
+        let (x1, y1) = (to.0 as isize, to.1 as isize);
+        let steps = max((x1 - x0).abs(), (y1 - y0).abs());
+        for step in 0..steps + 1 {
+            let x = x0 + (x1 - x0) * step / max(steps, 1);
+            let y = y0 + (y1 - y0) * step / max(steps, 1);
+            self.dot(x as usize, y as usize, ink);
+        }
+    }
+
+    fn rows(&self) -> Vec<Vec<(char, Ink)>> {
+        self.dots
+            .chunks(self.width)
+            .zip(self.inks.chunks(self.width))
+            .map(|(dots, inks)| {
+                dots.iter()
+                    .zip(inks.iter())
+                    .map(|(&dots, &ink)| {
+                        let symbol = ::std::char::from_u32(BRAILLE_BLANK + dots as u32).unwrap_or(' ');
+                        (symbol, ink)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/* Plot the run's deltas in `width` by `height` cells: the comparison as a line
+ * through the middle, the deltas joined up, and a mark for every split in
+ * the color of being ahead, behind or a gold */
+pub fn render(state: &graph::State, width: u16, height: u16) -> Vec<Vec<(char, Ink)>> {
+    let mut canvas = Canvas::new(width as usize, height as usize);
+    if width == 0 || height == 0 {
+        return canvas.rows();
+    }
+
+    /* Points come scaled to 0..1, with y growing downwards */
+    let dots_across = width as usize * DOTS_ACROSS;
+    let dots_down = height as usize * DOTS_DOWN;
+    let to_dot = |x: f32, y: f32| {
+        let x = (x.max(0.0).min(1.0) * (dots_across - 1) as f32).round() as usize;
+        let y = (y.max(0.0).min(1.0) * (dots_down - 1) as f32).round() as usize;
+        (min(x, dots_across - 1), min(y, dots_down - 1))
+    };
+
+    let (_, middle) = to_dot(0.0, state.middle);
+    for x in 0..dots_across {
+        canvas.dot(x, middle, Ink::Comparison);
+    }
+
+    let points = state.points.iter().map(|p| to_dot(p.x, p.y)).collect::<Vec<_>>();
+    for pair in points.windows(2) {
+        canvas.line(pair[0], pair[1], Ink::Line);
+    }
+
+    /* The first point is the start of the run, not a split */
+    for (point, &(x, y)) in state.points.iter().zip(points.iter()).skip(1) {
+        let behind = (y < middle) != state.is_flipped;
+        let ink = if point.is_best_segment {
+            Ink::Gold
+        } else if behind {
+            Ink::Behind
+        } else {
+            Ink::Ahead
+        };
+        canvas.dot(x, y, ink);
+    }
+
+    canvas.rows()
+}
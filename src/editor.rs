@@ -0,0 +1,248 @@
+use livesplit_core::{Run, RunEditor};
+use livesplit_core::run::editor::{SelectionState, State};
+use termion::event::Key;
+
+/* Editing a Run in the terminal, on top of livesplit-core's run editor */
+pub struct Editor {
+    editor: RunEditor,
+    input: Option<Input>,
+    error: Option<String>,
+    modified: bool,
+    /* Esc was pressed with unsaved changes, and needs pressing again */
+    confirm_discard: bool,
+}
+
+/* A field being typed into */
+pub struct Input {
+    pub field: Field,
+    pub text: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Field {
+    Game,
+    Category,
+    Offset,
+    Attempts,
+    Name,
+    SplitTime,
+    SegmentTime,
+    BestSegmentTime,
+}
+
+/* What to do after a key press */
+pub enum Outcome {
+    Continue,
+    Save,
+    Discard,
+}
+
+/* Keys shown at the bottom of the editor */
+pub const HELP: &'static str = "g game  c category  o offset  a attempts  enter rename  \
+                                s split  t segment  b best  i/I insert  d delete  [/] move  \
+                                w save  esc discard";
+
+impl Field {
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::Game => "Game",
+            Field::Category => "Category",
+            Field::Offset => "Offset",
+            Field::Attempts => "Attempts",
+            Field::Name => "Segment name",
+            Field::SplitTime => "Split time",
+            Field::SegmentTime => "Segment time",
+            Field::BestSegmentTime => "Best segment",
+        }
+    }
+}
+
+impl Editor {
+    pub fn new(run: Run) -> Editor {
+        let mut editor = RunEditor::new(run);
+        editor.select_only(0);
+        Editor {
+            editor: editor,
+            input: None,
+            error: None,
+            modified: false,
+            confirm_discard: false,
+        }
+    }
+
+    /* Go back to editing a run that couldn't be saved, keeping the changes */
+    pub fn reopen(run: Run, error: String) -> Editor {
+        let mut editor = Editor::new(run);
+        editor.modified = true;
+        editor.error = Some(error);
+        editor
+    }
+
+    pub fn state(&mut self) -> State {
+        self.editor.state()
+    }
+
+    pub fn input(&self) -> Option<&Input> {
+        self.input.as_ref()
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_ref().map(|e| e.as_str())
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    /* The edited Run */
+    pub fn close(self) -> Run {
+        self.editor.close()
+    }
+
+    pub fn handle_key(&mut self, key: Key) -> Outcome {
+        if self.input.is_some() {
+            self.handle_input_key(key);
+            return Outcome::Continue;
+        }
+
+        let confirm_discard = self.confirm_discard;
+        self.confirm_discard = false;
+        self.error = None;
+        match key {
+            Key::Char('w') => return Outcome::Save,
+            Key::Esc if self.modified && !confirm_discard => {
+                self.confirm_discard = true;
+                self.error = Some(String::from("Unsaved changes, press esc again to discard them"));
+            }
+            Key::Esc => return Outcome::Discard,
+            Key::Up => self.select(-1),
+            Key::Down => self.select(1),
+            Key::Char('g') => self.start_input(Field::Game),
+            Key::Char('c') => self.start_input(Field::Category),
+            Key::Char('o') => self.start_input(Field::Offset),
+            Key::Char('a') => self.start_input(Field::Attempts),
+            Key::Char('\n') => self.start_input(Field::Name),
+            Key::Char('s') => self.start_input(Field::SplitTime),
+            Key::Char('t') => self.start_input(Field::SegmentTime),
+            Key::Char('b') => self.start_input(Field::BestSegmentTime),
+            Key::Char('i') => {
+                self.editor.insert_segment_below();
+                self.modified = true;
+            }
+            Key::Char('I') => {
+                self.editor.insert_segment_above();
+                self.modified = true;
+            }
+            Key::Char('d') => {
+                if self.editor.can_remove_segments() {
+                    self.editor.remove_segments();
+                    self.modified = true;
+                } else {
+                    self.error = Some(String::from("A run needs at least one segment"));
+                }
+            }
+            Key::Char('[') => {
+                if self.editor.can_move_segments_up() {
+                    self.editor.move_segments_up();
+                    self.modified = true;
+                }
+            }
+            Key::Char(']') => {
+                if self.editor.can_move_segments_down() {
+                    self.editor.move_segments_down();
+                    self.modified = true;
+                }
+            }
+            _ => {}
+        }
+        Outcome::Continue
+    }
+
+    /* Move the selection by `offset` segments, staying in bounds */
+    fn select(&mut self, offset: isize) {
+        let state = self.editor.state();
+        let current = state.segments
+            .iter()
+            .position(|s| s.selected == SelectionState::Active)
+            .unwrap_or(0) as isize;
+        let last = state.segments.len() as isize - 1;
+        let index = (current + offset).max(0).min(last);
+        self.editor.select_only(index as usize);
+    }
+
+    /* Open a field for typing, starting from its current value */
+    fn start_input(&mut self, field: Field) {
+        let state = self.editor.state();
+        let text = {
+            let active = state.segments.iter().find(|s| s.selected == SelectionState::Active);
+            match field {
+                Field::Game => state.game.clone(),
+                Field::Category => state.category.clone(),
+                Field::Offset => state.offset.clone(),
+                Field::Attempts => state.attempts.to_string(),
+                Field::Name => active.map(|s| s.name.clone()).unwrap_or_default(),
+                Field::SplitTime => active.map(|s| s.split_time.clone()).unwrap_or_default(),
+                Field::SegmentTime => active.map(|s| s.segment_time.clone()).unwrap_or_default(),
+                Field::BestSegmentTime => active.map(|s| s.best_segment_time.clone()).unwrap_or_default(),
+            }
+        };
+
+        self.input = Some(Input {
+            field: field,
+            text: text,
+        });
+    }
+
+    fn handle_input_key(&mut self, key: Key) {
+        match key {
+            Key::Char('\n') => {
+                let input = self.input.take().unwrap();
+                if let Err(error) = self.apply(input.field, &input.text) {
+                    /* Leave the field open so the value can be fixed */
+                    self.error = Some(error);
+                    self.input = Some(input);
+                } else {
+                    self.error = None;
+                    self.modified = true;
+                }
+            }
+            Key::Esc => {
+                self.input = None;
+                self.error = None;
+            }
+            Key::Backspace => {
+                if let Some(ref mut input) = self.input {
+                    input.text.pop();
+                }
+            }
+            Key::Char(c) => {
+                if let Some(ref mut input) = self.input {
+                    input.text.push(c);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn apply(&mut self, field: Field, text: &str) -> Result<(), String> {
+        let invalid = || format!("Invalid {}: {}", field.label().to_lowercase(), text);
+
+        match field {
+            Field::Game => self.editor.set_game_name(text),
+            Field::Category => self.editor.set_category_name(text),
+            Field::Offset => self.editor.parse_and_set_offset(text).map_err(|_| invalid())?,
+            Field::Attempts => self.editor.parse_and_set_attempt_count(text).map_err(|_| invalid())?,
+            Field::Name => self.editor.active_segment().set_name(text),
+            Field::SplitTime => {
+                self.editor.active_segment().parse_and_set_split_time(text).map_err(|_| invalid())?
+            }
+            Field::SegmentTime => {
+                self.editor.active_segment().parse_and_set_segment_time(text).map_err(|_| invalid())?
+            }
+            Field::BestSegmentTime => {
+                self.editor.active_segment().parse_and_set_best_segment_time(text).map_err(|_| invalid())?
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,302 @@
+use chrono::{DateTime, Utc};
+use livesplit_core::{Run, TimeSpan, TimingMethod};
+use serde_json;
+use stats;
+use std::io::{self, Write};
+
+/* Splits in the JSON format of the Urn timer */
+#[derive(Serialize)]
+struct UrnSplits {
+    title: String,
+    attempt_count: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_delay: Option<String>,
+    splits: Vec<UrnSplit>,
+}
+
+#[derive(Serialize)]
+struct UrnSplit {
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    best_segment: Option<String>,
+}
+
+/* Write the personal best and best segments as Urn splits. Urn only has one
+ * timing method, so real time is used. */
+pub fn write_urn<W: Write>(run: &Run, writer: W) -> io::Result<()> {
+    let method = TimingMethod::RealTime;
+
+    /* Urn counts the start delay up from zero, LiveSplit counts the offset down to it */
+    let delay = -run.offset().total_seconds();
+
+    let splits = UrnSplits {
+        title: format!("{} {}", run.game_name(), run.category_name()).trim().to_string(),
+        attempt_count: run.attempt_count(),
+        start_delay: if delay > 0.0 { Some(urn_time(TimeSpan::from_seconds(delay))) } else { None },
+        splits: run.segments()
+            .iter()
+            .map(|segment| {
+                UrnSplit {
+                    title: segment.name().to_string(),
+                    time: segment.personal_best_split_time()[method].map(urn_time),
+                    best_segment: segment.best_segment_time()[method].map(urn_time),
+                }
+            })
+            .collect(),
+    };
+
+    serde_json::to_writer_pretty(writer, &splits).map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+}
+
+/* Urn reads times as h:mm:ss.ffffff */
+fn urn_time(time: TimeSpan) -> String {
+    let total = time.total_seconds();
+    let sign = if total < 0.0 { "-" } else { "" };
+    let total = total.abs();
+    let hours = (total / 3600.0).floor();
+    let minutes = ((total - hours * 3600.0) / 60.0).floor();
+    let seconds = total - hours * 3600.0 - minutes * 60.0;
+    format!("{}{}:{:02}:{:09.6}", sign, hours, minutes, seconds)
+}
+
+/* One row per segment with personal best and best segment times in seconds,
+ * for both timing methods */
+pub fn write_csv<W: Write>(run: &Run, mut writer: W) -> io::Result<()> {
+    write!(writer,
+           "Segment,Split Time (Real Time),Segment Time (Real Time),Best Segment (Real Time),\
+            Split Time (Game Time),Segment Time (Game Time),Best Segment (Game Time)\n")?;
+
+    let mut previous = [Some(TimeSpan::zero()), Some(TimeSpan::zero())];
+    for segment in run.segments() {
+        write!(writer, "{}", csv_field(segment.name()))?;
+
+        for (i, &method) in [TimingMethod::RealTime, TimingMethod::GameTime].iter().enumerate() {
+            let split = segment.personal_best_split_time()[method];
+            let segment_time = match (split, previous[i]) {
+                (Some(split), Some(previous)) => Some(split - previous),
+                _ => None,
+            };
+            previous[i] = split;
+
+            write!(writer, ",{},{},{}",
+                   csv_seconds(split),
+                   csv_seconds(segment_time),
+                   csv_seconds(segment.best_segment_time()[method]))?;
+        }
+        write!(writer, "\n")?;
+    }
+    Ok(())
+}
+
+/* A time in seconds for both timing methods */
+#[derive(Serialize)]
+struct Times {
+    real_time: Option<f64>,
+    game_time: Option<f64>,
+}
+
+/* Everything the stats subcommand knows about a run */
+#[derive(Serialize)]
+struct RunStats {
+    game: String,
+    category: String,
+    sum_of_best: Times,
+    segments: Vec<SegmentSummary>,
+    attempts: Vec<AttemptSummary>,
+}
+
+#[derive(Serialize)]
+struct SegmentSummary {
+    name: String,
+    best_segment: Times,
+}
+
+#[derive(Serialize)]
+struct AttemptSummary {
+    index: i32,
+    started: Option<String>,
+    ended: Option<String>,
+    time: Times,
+    pause_time: Option<f64>,
+    /* One per segment of the run */
+    segments: Vec<AttemptSegment>,
+}
+
+#[derive(Serialize)]
+struct AttemptSegment {
+    real_time: Option<f64>,
+    game_time: Option<f64>,
+    real_time_gold: bool,
+    game_time_gold: bool,
+}
+
+fn times(real_time: Option<TimeSpan>, game_time: Option<TimeSpan>) -> Times {
+    Times {
+        real_time: real_time.map(|t| t.total_seconds()),
+        game_time: game_time.map(|t| t.total_seconds()),
+    }
+}
+
+fn timestamp(time: Option<DateTime<Utc>>) -> Option<String> {
+    time.map(|t| t.to_rfc3339())
+}
+
+/* Attempts, their segment times and golds, best segments and the sum of best
+ * as a single JSON object */
+pub fn write_stats_json<W: Write>(run: &Run, writer: W) -> io::Result<()> {
+    let real_times = stats::attempt_segment_times(run, TimingMethod::RealTime);
+    let game_times = stats::attempt_segment_times(run, TimingMethod::GameTime);
+    let real_golds = stats::golds(run, TimingMethod::RealTime, &real_times);
+    let game_golds = stats::golds(run, TimingMethod::GameTime, &game_times);
+
+    let run_stats = RunStats {
+        game: run.game_name().to_string(),
+        category: run.category_name().to_string(),
+        sum_of_best: times(stats::sum_of_best(run, TimingMethod::RealTime),
+                           stats::sum_of_best(run, TimingMethod::GameTime)),
+        segments: run.segments()
+            .iter()
+            .map(|s| {
+                SegmentSummary {
+                    name: s.name().to_string(),
+                    best_segment: times(s.best_segment_time().real_time, s.best_segment_time().game_time),
+                }
+            })
+            .collect(),
+        attempts: run.attempt_history()
+            .iter()
+            .enumerate()
+            .map(|(i, attempt)| {
+                AttemptSummary {
+                    index: attempt.index(),
+                    started: timestamp(attempt.started().map(|t| t.time)),
+                    ended: timestamp(attempt.ended().map(|t| t.time)),
+                    time: times(attempt.time().real_time, attempt.time().game_time),
+                    pause_time: attempt.pause_time().map(|t| t.total_seconds()),
+                    segments: (0..run.segments().len())
+                        .map(|s| {
+                            AttemptSegment {
+                                real_time: real_times[i][s].map(|t| t.total_seconds()),
+                                game_time: game_times[i][s].map(|t| t.total_seconds()),
+                                real_time_gold: real_golds[i][s],
+                                game_time_gold: game_golds[i][s],
+                            }
+                        })
+                        .collect(),
+                }
+            })
+            .collect(),
+    };
+
+    serde_json::to_writer_pretty(writer, &run_stats).map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+}
+
+/* One row per segment of every attempt, then a Total row with the attempt's
+ * final time. The best segments and sum of best come last, as attempt "best". */
+pub fn write_stats_csv<W: Write>(run: &Run, mut writer: W) -> io::Result<()> {
+    write!(writer,
+           "Attempt,Started,Ended,Segment,Time (Real Time),Time (Game Time),Gold (Real Time),Gold (Game Time)\n")?;
+
+    let real_times = stats::attempt_segment_times(run, TimingMethod::RealTime);
+    let game_times = stats::attempt_segment_times(run, TimingMethod::GameTime);
+    let real_golds = stats::golds(run, TimingMethod::RealTime, &real_times);
+    let game_golds = stats::golds(run, TimingMethod::GameTime, &game_times);
+    let yes_no = |gold: bool| if gold { "yes" } else { "no" };
+
+    for (i, attempt) in run.attempt_history().iter().enumerate() {
+        let started = timestamp(attempt.started().map(|t| t.time)).unwrap_or_default();
+        let ended = timestamp(attempt.ended().map(|t| t.time)).unwrap_or_default();
+
+        for (s, segment) in run.segments().iter().enumerate() {
+            if real_times[i][s].is_none() && game_times[i][s].is_none() {
+                continue;
+            }
+            write!(writer, "{},{},{},{},{},{},{},{}\n",
+                   attempt.index(),
+                   started,
+                   ended,
+                   csv_field(segment.name()),
+                   csv_seconds(real_times[i][s]),
+                   csv_seconds(game_times[i][s]),
+                   yes_no(real_golds[i][s]),
+                   yes_no(game_golds[i][s]))?;
+        }
+        write!(writer, "{},{},{},Total,{},{},,\n",
+               attempt.index(),
+               started,
+               ended,
+               csv_seconds(attempt.time().real_time),
+               csv_seconds(attempt.time().game_time))?;
+    }
+
+    for segment in run.segments() {
+        write!(writer, "best,,,{},{},{},,\n",
+               csv_field(segment.name()),
+               csv_seconds(segment.best_segment_time().real_time),
+               csv_seconds(segment.best_segment_time().game_time))?;
+    }
+    write!(writer, "best,,,Total,{},{},,\n",
+           csv_seconds(stats::sum_of_best(run, TimingMethod::RealTime)),
+           csv_seconds(stats::sum_of_best(run, TimingMethod::GameTime)))
+}
+
+/* Times as seconds for spreadsheets, empty when missing */
+pub fn csv_seconds(time: Option<TimeSpan>) -> String {
+    time.map(|t| format!("{:.3}", t.total_seconds())).unwrap_or_default()
+}
+
+/* Quote a field if it would otherwise break the row */
+pub fn csv_field(text: &str) -> String {
+    if text.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use livesplit_core::{Segment, Time};
+
+    fn time(seconds: f64) -> Time {
+        Time::new().with_real_time(Some(TimeSpan::from_seconds(seconds)))
+    }
+
+    #[test]
+    fn quotes_csv_fields_that_need_it() {
+        assert_eq!(csv_field("Boss"), "Boss");
+        assert_eq!(csv_field("Boss, Phase 2"), "\"Boss, Phase 2\"");
+        assert_eq!(csv_field("The \"Boss\""), "\"The \"\"Boss\"\"\"");
+        assert_eq!(csv_field("Two\nlines"), "\"Two\nlines\"");
+    }
+
+    #[test]
+    fn writes_urn_times() {
+        assert_eq!(urn_time(TimeSpan::from_seconds(83.5)), "0:01:23.500000");
+        assert_eq!(urn_time(TimeSpan::from_seconds(3723.25)), "1:02:03.250000");
+        assert_eq!(urn_time(TimeSpan::from_seconds(-5.0)), "-0:00:05.000000");
+    }
+
+    #[test]
+    fn writes_a_csv_row_per_segment() {
+        let mut run = Run::new();
+        for &(name, split, best) in &[("Tutorial, skipped", 10.0, 9.0), ("Boss", 25.0, 14.0)] {
+            let mut segment = Segment::new(name);
+            segment.set_personal_best_split_time(time(split));
+            segment.set_best_segment_time(time(best));
+            run.push_segment(segment);
+        }
+
+        let mut csv = Vec::new();
+        write_csv(&run, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let rows = csv.lines().collect::<Vec<_>>();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].starts_with("Segment,Split Time (Real Time),"));
+        assert_eq!(rows[1], "\"Tutorial, skipped\",10.000,10.000,9.000,,,");
+        assert_eq!(rows[2], "Boss,25.000,15.000,14.000,,,");
+    }
+}
@@ -0,0 +1,187 @@
+use libc;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::os::unix::io::FromRawFd;
+use std::panic;
+use std::process;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+use termion::{cursor, style};
+use termion::screen::{ToAlternateScreen, ToMainScreen};
+use Event;
+
+/* Signals the main loop reacts to */
+pub enum Signal {
+    Interrupt,
+    Terminate,
+    HangUp,
+    Suspend,
+    Continue,
+    Resize,
+}
+
+/* Turn mouse reporting on and off, as termion's MouseTerminal does */
+const ENTER_MOUSE: &'static str = "\x1b[?1000h\x1b[?1002h\x1b[?1015h\x1b[?1006h";
+const EXIT_MOUSE: &'static str = "\x1b[?1006l\x1b[?1015l\x1b[?1002l\x1b[?1000l";
+
+/* Write end of the pipe signal handlers report to, -1 until there is one */
+static SIGNAL_PIPE: AtomicIsize = AtomicIsize::new(-1);
+
+/* Puts the terminal back the way it was found, whatever happens to the
+ * process: panics, signals, or being suspended with Ctrl-Z */
+pub struct TerminalGuard {
+    original: libc::termios,
+    raw: libc::termios,
+    mouse: bool,
+}
+
+impl TerminalGuard {
+    /* Remember the terminal settings from before raw mode, given by
+     * `attributes` beforehand, and the raw mode ones in use now. Switches to
+     * the alternate screen so the shell's scrollback is left alone. Panics
+     * outside the main thread are sent to `events`. */
+    pub fn new(original: libc::termios, mouse: bool, events: Sender<Event>) -> io::Result<TerminalGuard> {
+        let raw = attributes()?;
+        let guard = TerminalGuard {
+            original: original,
+            raw: raw,
+            mouse: mouse,
+        };
+        guard.enter();
+
+        /* Other threads hand their panic to the main loop, which saves the run
+         * on its way out. The main thread can't carry on, so it restores before
+         * the message is printed, so it can be read. */
+        let events = Mutex::new(events);
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if thread::current().name() != Some("main") {
+                if let Ok(events) = events.lock() {
+                    if events.send(Event::Panic(info.to_string())).is_ok() {
+                        return;
+                    }
+                }
+            }
+            restore(&original);
+            default_hook(info);
+            process::exit(101);
+        }));
+
+        Ok(guard)
+    }
+
+    /* Leave raw mode and the alternate screen, and show the cursor */
+    pub fn restore(&self) {
+        restore(&self.original);
+    }
+
+    /* Stop the process as the shell expects on Ctrl-Z, picking up again once
+     * it's continued. The screen needs a full redraw afterwards. */
+    pub fn suspend(&self) {
+        self.restore();
+        unsafe {
+            libc::signal(libc::SIGTSTP, libc::SIG_DFL);
+            libc::raise(libc::SIGTSTP);
+            libc::signal(libc::SIGTSTP, on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t);
+        }
+        self.resume();
+    }
+
+    /* Go back to raw mode after being continued. The shell may have changed
+     * the terminal settings in the meantime. */
+    pub fn resume(&self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.raw);
+        }
+        self.enter();
+    }
+
+    fn enter(&self) {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        let _ = write!(stdout, "{}{}", ToAlternateScreen, cursor::Hide);
+        if self.mouse {
+            let _ = write!(stdout, "{}", ENTER_MOUSE);
+        }
+        let _ = stdout.flush();
+    }
+}
+
+/* Current settings of the terminal on stdin */
+pub fn attributes() -> io::Result<libc::termios> {
+    unsafe {
+        let mut attributes: libc::termios = mem::zeroed();
+        if libc::tcgetattr(libc::STDIN_FILENO, &mut attributes) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(attributes)
+    }
+}
+
+/* Errors are ignored, the terminal may already be gone after a hang up */
+fn restore(original: &libc::termios) {
+    unsafe {
+        libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original);
+    }
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let _ = write!(stdout, "{}{}{}{}", EXIT_MOUSE, style::Reset, cursor::Show, ToMainScreen);
+    let _ = stdout.flush();
+}
+
+/* Turn signals into events for the main loop. Handlers may only do very
+ * little, so they write the signal number to a pipe that a thread reads.
+ * Suspending and resizing only matter with a terminal to draw on. */
+pub fn forward_signals(events: Sender<Event>, interactive: bool) -> io::Result<()> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    SIGNAL_PIPE.store(fds[1] as isize, Ordering::SeqCst);
+
+    let mut signals = vec![libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+    if interactive {
+        signals.push(libc::SIGTSTP);
+        signals.push(libc::SIGCONT);
+        signals.push(libc::SIGWINCH);
+    }
+    for &signal in &signals {
+        unsafe {
+            libc::signal(signal, on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t);
+        }
+    }
+
+    let mut pipe = unsafe { File::from_raw_fd(fds[0]) };
+    thread::spawn(move || {
+        let mut byte = [0; 1];
+        while let Ok(1) = pipe.read(&mut byte) {
+            let signal = match byte[0] as libc::c_int {
+                libc::SIGINT => Signal::Interrupt,
+                libc::SIGTERM => Signal::Terminate,
+                libc::SIGHUP => Signal::HangUp,
+                libc::SIGTSTP => Signal::Suspend,
+                libc::SIGCONT => Signal::Continue,
+                libc::SIGWINCH => Signal::Resize,
+                _ => continue,
+            };
+            if events.send(Event::Signal(signal)).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+extern "C" fn on_signal(signal: libc::c_int) {
+    let byte = signal as u8;
+    let fd = SIGNAL_PIPE.load(Ordering::SeqCst) as libc::c_int;
+    if fd < 0 {
+        return;
+    }
+    unsafe {
+        libc::write(fd, &byte as *const u8 as *const libc::c_void, 1);
+    }
+}
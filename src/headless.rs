@@ -0,0 +1,114 @@
+use guard::Signal;
+use layout::{Layout, ComponentState};
+use livesplit_core::{SharedTimer, TimerPhase};
+use livesplit_core::layout::GeneralSettings;
+use run_file::RunFile;
+use serde_json::{self, Map, Value};
+use server;
+use std::io::{self, Write};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use {catch_up_with_hotkeys, Event, Snapshot};
+
+/* Run without a terminal. Commands are read from stdin, one per line, in the
+ * LiveSplit Server protocol. With `json` set, the state of every component is
+ * written to stdout as one JSON object per line, every `interval` and after
+ * every command. Returns once stdin is closed. */
+pub fn run(timer: SharedTimer, mut layout: Layout, layout_settings: &GeneralSettings,
+           mut run_file: RunFile, events: Sender<Event>, receiver: Receiver<Event>,
+           json: bool, interval: Duration) -> Result<(), String> {
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        server::serve_with(stdin.lock(),
+                           |response| {
+                               let mut response_json = Map::new();
+                               response_json.insert(String::from("response"), Value::from(response));
+                               print_line(&Value::Object(response_json))
+                           },
+                           events.clone());
+        let _ = events.send(Event::Quit);
+    });
+
+    let mut next_tick = Instant::now();
+    let mut seen = Snapshot::take(&timer);
+    loop {
+        let now = Instant::now();
+        let timeout = if next_tick > now { next_tick - now } else { Duration::from_millis(0) };
+        let event = receiver.recv_timeout(timeout);
+        seen = catch_up_with_hotkeys(&timer, &mut run_file, &seen);
+        match event {
+            Ok(Event::Command(request)) => {
+                let response = request.command.execute(&timer, &mut run_file);
+                let _ = request.reply.send(response);
+                seen = Snapshot::take(&timer);
+            }
+            Ok(Event::Signal(Signal::Interrupt)) |
+            Ok(Event::Signal(Signal::Terminate)) |
+            Ok(Event::Signal(Signal::HangUp)) => break,
+            Ok(Event::Key(_)) | Ok(Event::Mouse(_)) | Ok(Event::Signal(_)) => continue,
+            Ok(Event::Quit) | Ok(Event::Panic(_)) | Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => next_tick = Instant::now() + interval,
+        }
+
+        if json && print_line(&state_json(&timer, &mut layout, layout_settings, &run_file)).is_err() {
+            break;
+        }
+    }
+
+    if run_file.is_modified() {
+        run_file.save(timer.read().run())?;
+    }
+    Ok(())
+}
+
+/* {"phase": ..., "practice": ..., "components": [{"Title": {...}}, {"Splits": {...}}, ...]} */
+fn state_json(timer: &SharedTimer, layout: &mut Layout, layout_settings: &GeneralSettings,
+              run_file: &RunFile) -> Value {
+    let phase = match timer.read().current_phase() {
+        TimerPhase::NotRunning => "NotRunning",
+        TimerPhase::Running => "Running",
+        TimerPhase::Paused => "Paused",
+        TimerPhase::Ended => "Ended",
+    };
+
+    let components = layout.states(layout_settings)
+        .iter()
+        .map(|state| {
+            let (name, value) = match *state {
+                ComponentState::Title(ref s) => ("Title", serde_json::to_value(s)),
+                ComponentState::Splits(ref s) => ("Splits", serde_json::to_value(s)),
+                ComponentState::Timer(ref s, _) => ("Timer", serde_json::to_value(s)),
+                ComponentState::PreviousSegment(ref s) => ("PreviousSegment", serde_json::to_value(s)),
+                ComponentState::SumOfBest(ref s) => ("SumOfBest", serde_json::to_value(s)),
+                ComponentState::PossibleTimeSave(ref s) => ("PossibleTimeSave", serde_json::to_value(s)),
+                ComponentState::CurrentComparison(ref s) => ("CurrentComparison", serde_json::to_value(s)),
+                ComponentState::CurrentPace(ref s) => ("CurrentPace", serde_json::to_value(s)),
+                ComponentState::Delta(ref s) => ("Delta", serde_json::to_value(s)),
+                ComponentState::TotalPlaytime(ref s) => ("TotalPlaytime", serde_json::to_value(s)),
+                ComponentState::DetailedTimer(ref s, _) => ("DetailedTimer", serde_json::to_value(s)),
+                ComponentState::Text(ref s) => ("Text", serde_json::to_value(s)),
+                ComponentState::Separator(ref s) => ("Separator", serde_json::to_value(s)),
+                ComponentState::BlankSpace(ref s, _) => ("BlankSpace", serde_json::to_value(s)),
+                ComponentState::Graph(ref s, _) => ("Graph", serde_json::to_value(s)),
+            };
+            let mut component = Map::new();
+            component.insert(String::from(name), value.unwrap_or(Value::Null));
+            Value::Object(component)
+        })
+        .collect();
+
+    let mut state = Map::new();
+    state.insert(String::from("phase"), Value::from(phase));
+    state.insert(String::from("practice"), Value::from(run_file.is_practicing()));
+    state.insert(String::from("components"), Value::Array(components));
+    Value::Object(state)
+}
+
+/* Write a whole line at once, so responses and states never interleave */
+fn print_line(value: &Value) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    write!(stdout, "{}\n", value)?;
+    stdout.flush()
+}
@@ -0,0 +1,255 @@
+use stats::{AttemptStats, SegmentStats};
+use std::cmp::Ordering;
+use termion::event::Key;
+
+/* Browsing how each segment went in past attempts, to find the ones most
+ * worth practicing */
+pub struct SegmentHistory {
+    /* Index of the selected segment in the run */
+    pub segment: usize,
+    pub sort: SortBy,
+    pub descending: bool,
+    /* Newer times of the selected segment scrolled past */
+    pub scroll: usize,
+}
+
+/* Column the segment list is sorted by */
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortBy {
+    Order,
+    Best,
+    Average,
+    Median,
+    StdDev,
+    Count,
+}
+
+/* Keys shown at the bottom of the segment history */
+pub const SEGMENT_HELP: &'static str = "up/down segment  s sort  r reverse  pgup/pgdn scroll times  esc close";
+
+impl SortBy {
+    pub fn label(self) -> &'static str {
+        match self {
+            SortBy::Order => "Segment",
+            SortBy::Best => "Best",
+            SortBy::Average => "Average",
+            SortBy::Median => "Median",
+            SortBy::StdDev => "Std dev",
+            SortBy::Count => "Count",
+        }
+    }
+
+    fn next(self) -> SortBy {
+        match self {
+            SortBy::Order => SortBy::Best,
+            SortBy::Best => SortBy::Average,
+            SortBy::Average => SortBy::Median,
+            SortBy::Median => SortBy::StdDev,
+            SortBy::StdDev => SortBy::Count,
+            SortBy::Count => SortBy::Order,
+        }
+    }
+}
+
+impl SegmentHistory {
+    pub fn new(segment: usize) -> SegmentHistory {
+        SegmentHistory {
+            segment: segment,
+            sort: SortBy::Order,
+            descending: false,
+            scroll: 0,
+        }
+    }
+
+    /* Indices of the segments in the order they're listed. Segments without
+     * the stat being sorted by go last. */
+    pub fn order(&self, stats: &[SegmentStats]) -> Vec<usize> {
+        let mut order = (0..stats.len()).collect::<Vec<_>>();
+        let key = |i: &usize| -> Option<f64> {
+            let s = &stats[*i];
+            match self.sort {
+                SortBy::Order => Some(*i as f64),
+                SortBy::Best => s.best.map(|t| t.total_seconds()),
+                SortBy::Average => s.average.map(|t| t.total_seconds()),
+                SortBy::Median => s.median.map(|t| t.total_seconds()),
+                SortBy::StdDev => s.std_dev.map(|t| t.total_seconds()),
+                SortBy::Count => Some(s.count() as f64),
+            }
+        };
+        order.sort_by(|a, b| {
+            match (key(a), key(b)) {
+                (Some(a), Some(b)) => {
+                    let ordering = a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+                    if self.descending { ordering.reverse() } else { ordering }
+                }
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        });
+        order
+    }
+
+    /* Returns false once the view is closed */
+    pub fn handle_key(&mut self, key: Key, stats: &[SegmentStats]) -> bool {
+        match key {
+            Key::Esc | Key::Char('q') | Key::Char('h') => return false,
+            Key::Up => self.select(-1, stats),
+            Key::Down => self.select(1, stats),
+            Key::Char('s') => self.sort = self.sort.next(),
+            Key::Char('r') => self.descending = !self.descending,
+            Key::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            Key::PageDown => {
+                let count = stats.get(self.segment).map(|s| s.count()).unwrap_or(0);
+                self.scroll = (self.scroll + 10).min(count.saturating_sub(1));
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /* Move the selection by `offset` rows of the sorted list */
+    fn select(&mut self, offset: isize, stats: &[SegmentStats]) {
+        let order = self.order(stats);
+        if order.is_empty() {
+            return;
+        }
+        let current = order.iter().position(|&i| i == self.segment).unwrap_or(0) as isize;
+        let row = (current + offset).max(0).min(order.len() as isize - 1);
+        self.segment = order[row as usize];
+        self.scroll = 0;
+    }
+}
+
+/* Browsing past attempts and what they add up to */
+pub struct AttemptHistory {
+    pub page: Page,
+    /* Rows of the page scrolled past */
+    pub scroll: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Page {
+    Attempts,
+    Resets,
+    Progression,
+}
+
+pub const PAGES: [Page; 3] = [Page::Attempts, Page::Resets, Page::Progression];
+
+/* Keys shown at the bottom of the attempt history */
+pub const ATTEMPT_HELP: &'static str = "tab page  up/down/pgup/pgdn scroll  esc close";
+
+impl Page {
+    pub fn label(self) -> &'static str {
+        match self {
+            Page::Attempts => "Attempts",
+            Page::Resets => "Resets",
+            Page::Progression => "PB progression",
+        }
+    }
+
+    fn next(self) -> Page {
+        match self {
+            Page::Attempts => Page::Resets,
+            Page::Resets => Page::Progression,
+            Page::Progression => Page::Attempts,
+        }
+    }
+
+    /* Rows of the page, to know how far it scrolls */
+    fn rows(self, stats: &AttemptStats) -> usize {
+        match self {
+            Page::Attempts => stats.attempts.len(),
+            Page::Resets => stats.resets.len(),
+            Page::Progression => stats.pb_progression.len(),
+        }
+    }
+}
+
+impl AttemptHistory {
+    pub fn new() -> AttemptHistory {
+        AttemptHistory {
+            page: Page::Attempts,
+            scroll: 0,
+        }
+    }
+
+    /* Returns false once the view is closed */
+    pub fn handle_key(&mut self, key: Key, stats: &AttemptStats) -> bool {
+        let last = self.page.rows(stats).saturating_sub(1);
+        match key {
+            Key::Esc | Key::Char('q') | Key::Char('a') => return false,
+            Key::Char('\t') => {
+                self.page = self.page.next();
+                self.scroll = 0;
+            }
+            Key::Up => self.scroll = self.scroll.saturating_sub(1),
+            Key::Down => self.scroll = (self.scroll + 1).min(last),
+            Key::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            Key::PageDown => self.scroll = (self.scroll + 10).min(last),
+            _ => {}
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use livesplit_core::TimeSpan;
+
+    /* A segment with this best time and this many times */
+    fn segment(best: Option<f64>, count: usize) -> SegmentStats {
+        SegmentStats {
+            name: String::new(),
+            times: (0..count).map(|i| (i as i32, TimeSpan::from_seconds(60.0))).collect(),
+            best: best.map(TimeSpan::from_seconds),
+            average: None,
+            median: None,
+            std_dev: None,
+        }
+    }
+
+    fn stats() -> Vec<SegmentStats> {
+        vec![segment(Some(30.0), 2), segment(None, 0), segment(Some(10.0), 3)]
+    }
+
+    #[test]
+    fn lists_segments_in_run_order_by_default() {
+        let mut view = SegmentHistory::new(0);
+        assert_eq!(view.order(&stats()), [0, 1, 2]);
+        view.descending = true;
+        assert_eq!(view.order(&stats()), [2, 1, 0]);
+    }
+
+    #[test]
+    fn segments_without_the_stat_go_last() {
+        let mut view = SegmentHistory::new(0);
+        view.sort = SortBy::Best;
+        assert_eq!(view.order(&stats()), [2, 0, 1]);
+        view.descending = true;
+        assert_eq!(view.order(&stats()), [0, 2, 1]);
+        view.sort = SortBy::Median;
+        assert_eq!(view.order(&stats()), [0, 1, 2]);
+    }
+
+    #[test]
+    fn sorts_by_count() {
+        let mut view = SegmentHistory::new(0);
+        view.sort = SortBy::Count;
+        assert_eq!(view.order(&stats()), [1, 0, 2]);
+    }
+
+    #[test]
+    fn selection_follows_the_sorted_list() {
+        let mut view = SegmentHistory::new(2);
+        view.sort = SortBy::Best;
+        assert!(view.handle_key(Key::Down, &stats()));
+        assert_eq!(view.segment, 0);
+        view.handle_key(Key::Down, &stats());
+        view.handle_key(Key::Down, &stats());
+        assert_eq!(view.segment, 1);
+        assert!(!view.handle_key(Key::Esc, &stats()));
+    }
+}
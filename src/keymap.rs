@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use termion::event::Key;
+
+/* Everything a key can be bound to */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    SplitOrStart,
+    SkipSplit,
+    Reset,
+    PreviousComparison,
+    TogglePauseOrStart,
+    NextComparison,
+    PickComparison,
+    UndoSplit,
+    ToggleTimingMethod,
+    ToggleGameTimePause,
+    SetLoadingTimes,
+    Edit,
+    SegmentHistory,
+    AttemptHistory,
+    TogglePractice,
+    ScrollUp,
+    ScrollDown,
+    Save,
+    Quit,
+}
+
+/* Config names and default keys of each action */
+const ACTIONS: &'static [(Action, &'static str, &'static [&'static str])] = &[
+    (Action::SplitOrStart, "split", &["1"]),
+    (Action::SkipSplit, "skip", &["2"]),
+    (Action::Reset, "reset", &["3"]),
+    (Action::PreviousComparison, "previous_comparison", &["4"]),
+    (Action::TogglePauseOrStart, "pause", &["5"]),
+    (Action::NextComparison, "next_comparison", &["6"]),
+    (Action::PickComparison, "pick_comparison", &["c"]),
+    (Action::UndoSplit, "undo", &["8"]),
+    (Action::ToggleTimingMethod, "toggle_timing_method", &["t"]),
+    (Action::ToggleGameTimePause, "toggle_game_time_pause", &["g"]),
+    (Action::SetLoadingTimes, "set_loading_times", &["l"]),
+    (Action::Edit, "edit", &["e"]),
+    (Action::SegmentHistory, "segment_history", &["h"]),
+    (Action::AttemptHistory, "attempt_history", &["a"]),
+    (Action::TogglePractice, "toggle_practice", &["p"]),
+    (Action::ScrollUp, "scroll_up", &["Up"]),
+    (Action::ScrollDown, "scroll_down", &["Down"]),
+    (Action::Save, "save", &["s"]),
+    (Action::Quit, "quit", &["q"]),
+];
+
+pub struct Keymap {
+    bindings: HashMap<Key, Action>,
+}
+
+impl Keymap {
+    /* Build a keymap from action names to key names. Actions that are not
+     * mentioned keep those of their default keys that weren't bound to
+     * something else. Only binding one key to two actions is an error. */
+    pub fn new(overrides: &HashMap<String, Vec<String>>) -> Result<Keymap, String> {
+        for name in overrides.keys() {
+            if !ACTIONS.iter().any(|&(_, n, _)| n == name) {
+                return Err(format!("Unknown action \"{}\" in key bindings", name));
+            }
+        }
+
+        let mut bindings = HashMap::new();
+        for &(action, name, _) in ACTIONS {
+            let keys = match overrides.get(name) {
+                Some(keys) => keys,
+                None => continue,
+            };
+            for key_name in keys {
+                let key = parse_key(key_name)?;
+                if let Some(other) = bindings.insert(key, action) {
+                    if other != action {
+                        return Err(format!("Key \"{}\" is bound to both {} and {}",
+                                           key_name, action_name(other), name));
+                    }
+                }
+            }
+        }
+
+        for &(action, name, defaults) in ACTIONS {
+            if !overrides.contains_key(name) {
+                for key_name in defaults {
+                    bindings.entry(parse_key(key_name)?).or_insert(action);
+                }
+            }
+        }
+
+        Ok(Keymap { bindings: bindings })
+    }
+
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.bindings.get(&key).cloned()
+    }
+}
+
+fn action_name(action: Action) -> &'static str {
+    ACTIONS.iter().find(|&&(a, _, _)| a == action).unwrap().1
+}
+
+/* Parse key names such as "q", "Space", "F5", "PageUp", "Ctrl-s" or "Alt-x".
+ * Ctrl-C and Ctrl-Z always quit and suspend, so they can't be bound. */
+pub fn parse_key(name: &str) -> Result<Key, String> {
+    let invalid = || format!("Invalid key \"{}\"", name);
+
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(Key::Char(c));
+    }
+
+    let lower = name.to_lowercase();
+    for &(prefix, ctrl) in &[("ctrl-", true), ("c-", true), ("alt-", false), ("m-", false)] {
+        if lower.starts_with(prefix) {
+            let mut rest = name[prefix.len()..].chars();
+            return match (rest.next(), rest.next()) {
+                (Some(c), None) if ctrl => {
+                    match c.to_ascii_lowercase() {
+                        'c' | 'z' => Err(format!("Key \"{}\" is reserved for quitting and suspending", name)),
+                        c => Ok(Key::Ctrl(c)),
+                    }
+                }
+                (Some(c), None) => Ok(Key::Alt(c)),
+                _ => Err(invalid()),
+            };
+        }
+    }
+
+    if lower.starts_with('f') {
+        if let Ok(n) = lower[1..].parse::<u8>() {
+            if n >= 1 && n <= 12 {
+                return Ok(Key::F(n));
+            }
+        }
+    }
+
+    Ok(match lower.as_str() {
+        "space" => Key::Char(' '),
+        "enter" | "return" => Key::Char('\n'),
+        "tab" => Key::Char('\t'),
+        "esc" | "escape" => Key::Esc,
+        "backspace" => Key::Backspace,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "delete" => Key::Delete,
+        "insert" => Key::Insert,
+        _ => return Err(invalid()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_single_characters() {
+        assert_eq!(parse_key("s"), Ok(Key::Char('s')));
+        assert_eq!(parse_key("S"), Ok(Key::Char('S')));
+    }
+
+    #[test]
+    fn parses_modifiers() {
+        assert_eq!(parse_key("ctrl-A"), Ok(Key::Ctrl('a')));
+        assert_eq!(parse_key("C-x"), Ok(Key::Ctrl('x')));
+        assert_eq!(parse_key("alt-X"), Ok(Key::Alt('X')));
+        assert!(parse_key("ctrl-ab").is_err());
+    }
+
+    #[test]
+    fn parses_named_keys() {
+        assert_eq!(parse_key("Space"), Ok(Key::Char(' ')));
+        assert_eq!(parse_key("enter"), Ok(Key::Char('\n')));
+        assert_eq!(parse_key("PageDown"), Ok(Key::PageDown));
+        assert_eq!(parse_key("f12"), Ok(Key::F(12)));
+        assert!(parse_key("f13").is_err());
+        assert!(parse_key("nope").is_err());
+    }
+
+    #[test]
+    fn rejects_reserved_keys() {
+        assert!(parse_key("ctrl-c").is_err());
+        assert!(parse_key("C-Z").is_err());
+    }
+
+    fn bindings(pairs: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        pairs.iter()
+            .map(|&(action, keys)| (action.to_string(), keys.iter().map(|k| k.to_string()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn bindings_take_keys_from_defaults() {
+        let keymap = Keymap::new(&bindings(&[("split", &["s"])])).unwrap();
+        assert_eq!(keymap.action(Key::Char('s')), Some(Action::SplitOrStart));
+        assert_eq!(keymap.action(Key::Char('1')), None);
+        assert_eq!(keymap.action(Key::Char('q')), Some(Action::Quit));
+    }
+
+    #[test]
+    fn rejects_keys_bound_twice() {
+        assert!(Keymap::new(&bindings(&[("split", &["s"]), ("save", &["s"])])).is_err());
+        assert!(Keymap::new(&bindings(&[("splitt", &["s"])])).is_err());
+    }
+}
@@ -0,0 +1,746 @@
+use livesplit_core::{SharedTimer, Timer};
+use livesplit_core::component::{timer, splits, title, previous_segment, sum_of_best,
+                                possible_time_save, current_comparison, current_pace, delta,
+                                total_playtime, detailed_timer, text, separator, blank_space,
+                                graph};
+use livesplit_core::layout::GeneralSettings;
+use serde_json::{self, Value};
+use std::cmp::min;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use sxd_document::dom::Element;
+use sxd_document::parser;
+
+pub struct Layout {
+    pub timer: SharedTimer,
+    pub components: Vec<Component>,
+}
+
+/* A livesplit-core component, along with the settings we apply on top of it */
+pub enum Component {
+    Title(title::Component),
+    Splits(SplitsComponent),
+    Timer(timer::Component, Accuracy, u16),
+    PreviousSegment(previous_segment::Component, Accuracy),
+    SumOfBest(sum_of_best::Component, Accuracy),
+    PossibleTimeSave(possible_time_save::Component, Accuracy),
+    CurrentComparison(current_comparison::Component),
+    CurrentPace(current_pace::Component, Accuracy),
+    Delta(delta::Component, Accuracy),
+    TotalPlaytime(total_playtime::Component),
+    DetailedTimer(detailed_timer::Component, Accuracy, u16),
+    Text(text::Component),
+    Separator(separator::Component),
+    BlankSpace(blank_space::Component, u16),
+    Graph(graph::Component, u16),
+}
+
+/* The splits component keeps the settings it was configured with, so the
+ * number of visible splits can be shrunk to fit the terminal and grown back */
+pub struct SplitsComponent {
+    component: splits::Component,
+    settings: splits::Settings,
+    visual_split_count: usize,
+}
+
+/* The state of each component for a single frame */
+pub enum ComponentState {
+    Title(title::State),
+    Splits(splits::State),
+    /* Along with the rows it asks for */
+    Timer(timer::State, u16),
+    PreviousSegment(previous_segment::State),
+    SumOfBest(sum_of_best::State),
+    PossibleTimeSave(possible_time_save::State),
+    CurrentComparison(current_comparison::State),
+    CurrentPace(current_pace::State),
+    Delta(delta::State),
+    TotalPlaytime(total_playtime::State),
+    /* Along with the rows the main timer asks for */
+    DetailedTimer(detailed_timer::State, u16),
+    Text(text::State),
+    Separator(separator::State),
+    /* Along with the rows to leave empty */
+    BlankSpace(blank_space::State, u16),
+    /* Along with the rows it asks for */
+    Graph(graph::State, u16),
+}
+
+/* Ordered component list, as read from a layout file. JSON layouts are
+ * livesplit-core's layout settings:
+ *
+ *     {"components": [{"Title": {}}, {"Splits": {"visual_split_count": 10}}, ...],
+ *      "general": {...}}
+ *
+ * Each component is an object named after its type, holding the settings
+ * below. Anything else livesplit-core stores, such as colors, pixel sizes and
+ * the general settings, has no meaning in a terminal and is ignored, as are
+ * component types we can't draw. */
+#[derive(Debug)]
+pub struct LayoutSettings {
+    pub components: Vec<ComponentSettings>,
+}
+
+/* A JSON layout, before the components we can't draw are dropped */
+#[derive(Deserialize)]
+struct JsonLayout {
+    components: Vec<Value>,
+}
+
+/* The component types of livesplit-core we can draw, as named in JSON */
+const COMPONENT_NAMES: &'static [&'static str] = &["Title", "Splits", "Timer", "PreviousSegment",
+                                                   "SumOfBest", "PossibleTimeSave", "CurrentComparison",
+                                                   "CurrentPace", "Delta", "TotalPlaytime", "DetailedTimer",
+                                                   "Text", "Separator", "BlankSpace", "Graph"];
+
+#[derive(Deserialize, Debug)]
+pub enum ComponentSettings {
+    Title(NoSettings),
+    Splits(SplitsSettings),
+    Timer(TimerSettings),
+    PreviousSegment(InfoSettings),
+    SumOfBest(InfoSettings),
+    PossibleTimeSave(InfoSettings),
+    CurrentComparison(NoSettings),
+    CurrentPace(InfoSettings),
+    Delta(InfoSettings),
+    TotalPlaytime(NoSettings),
+    DetailedTimer(TimerSettings),
+    Text(TextSettings),
+    Separator(NoSettings),
+    BlankSpace(HeightSettings),
+    Graph(GraphSettings),
+}
+
+/* For components with nothing we can set */
+#[derive(Deserialize, Default, Debug)]
+pub struct NoSettings {}
+
+/* Anything left out falls back to the config file, then livesplit-core's
+ * default. A visual split count of 0 shows as many splits as fit. */
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct SplitsSettings {
+    pub visual_split_count: Option<usize>,
+    /* Upcoming splits to show below the current one */
+    pub split_preview_count: Option<usize>,
+    /* Pin the final split to the bottom of the window */
+    pub always_show_last_split: Option<bool>,
+    pub separator_last_split: Option<bool>,
+}
+
+impl SplitsSettings {
+    /* Fill in anything not set here from `defaults` */
+    pub fn or(&self, defaults: &SplitsSettings) -> SplitsSettings {
+        SplitsSettings {
+            visual_split_count: self.visual_split_count.or(defaults.visual_split_count),
+            split_preview_count: self.split_preview_count.or(defaults.split_preview_count),
+            always_show_last_split: self.always_show_last_split.or(defaults.always_show_last_split),
+            separator_last_split: self.separator_last_split.or(defaults.separator_last_split),
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct TimerSettings {
+    pub accuracy: Option<Accuracy>,
+    /* Rows to draw the timer in. From 3 rows up it's drawn in block digits. */
+    pub rows: Option<u16>,
+}
+
+impl TimerSettings {
+    /* Fill in anything not set here from `defaults` */
+    pub fn or(&self, defaults: &TimerSettings) -> TimerSettings {
+        TimerSettings {
+            accuracy: self.accuracy.or(defaults.accuracy),
+            rows: self.rows.or(defaults.rows),
+        }
+    }
+}
+
+/* Settings shared by the single line text components */
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct InfoSettings {
+    pub comparison_override: Option<String>,
+    pub accuracy: Accuracy,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct TextSettings {
+    pub text: TextContent,
+}
+
+/* A line of text, as {"Center": "..."} or {"Split": ["left", "right"]} */
+#[derive(Deserialize, Clone, Debug)]
+pub enum TextContent {
+    Center(String),
+    Split(String, String),
+}
+
+impl Default for TextContent {
+    fn default() -> TextContent {
+        TextContent::Center(String::new())
+    }
+}
+
+/* Sizes are given in rows, under their own key, as livesplit-core's height
+ * is in pixels */
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct HeightSettings {
+    pub rows: Option<u16>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct GraphSettings {
+    pub comparison_override: Option<String>,
+    pub rows: Option<u16>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Accuracy {
+    Seconds,
+    Tenths,
+    Hundredths,
+}
+
+impl Default for Accuracy {
+    fn default() -> Accuracy {
+        Accuracy::Hundredths
+    }
+}
+
+impl Accuracy {
+    fn parse(name: &str) -> Option<Accuracy> {
+        match name {
+            "Seconds" => Some(Accuracy::Seconds),
+            "Tenths" => Some(Accuracy::Tenths),
+            "Hundredths" | "Milliseconds" => Some(Accuracy::Hundredths),
+            _ => None,
+        }
+    }
+
+    /* Cut a formatted time such as "1:23.45" or ".45" down to this accuracy */
+    pub fn apply(self, time: &str) -> String {
+        let digits = match self {
+            Accuracy::Seconds => 0,
+            Accuracy::Tenths => 1,
+            Accuracy::Hundredths => 2,
+        };
+
+        match time.rfind('.') {
+            Some(dot) if digits == 0 => time[..dot].to_string(),
+            Some(dot) => {
+                let fraction: String = time[dot + 1..].chars().take(digits).collect();
+                format!("{}.{}", &time[..dot], fraction)
+            }
+            None => time.to_string(),
+        }
+    }
+}
+
+impl Default for LayoutSettings {
+    /* Title, splits, timer, current comparison, previous segment, sum of best
+     * and possible time save */
+    fn default() -> LayoutSettings {
+        LayoutSettings {
+            components: vec![
+                ComponentSettings::Title(NoSettings::default()),
+                ComponentSettings::Splits(SplitsSettings::default()),
+                ComponentSettings::Timer(TimerSettings::default()),
+                ComponentSettings::CurrentComparison(NoSettings::default()),
+                ComponentSettings::PreviousSegment(InfoSettings::default()),
+                ComponentSettings::SumOfBest(InfoSettings::default()),
+                ComponentSettings::PossibleTimeSave(InfoSettings::default()),
+            ],
+        }
+    }
+}
+
+impl LayoutSettings {
+    /* Use the config file's settings for anything the layout leaves out */
+    pub fn with_defaults(mut self, splits: &SplitsSettings, timer: &TimerSettings) -> LayoutSettings {
+        for component in &mut self.components {
+            match *component {
+                ComponentSettings::Splits(ref mut settings) => *settings = settings.or(splits),
+                ComponentSettings::Timer(ref mut settings) => *settings = settings.or(timer),
+                _ => {}
+            }
+        }
+        self
+    }
+
+    /* Read a LiveSplit .lsl file, or livesplit-core layout JSON if the name ends in .json */
+    pub fn load(path: &Path) -> Result<LayoutSettings, String> {
+        let mut contents = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .map_err(|e| format!("Unable to open {}: {}", path.display(), e))?;
+
+        let is_json = path.extension().map(|ext| ext == "json").unwrap_or(false);
+        let settings = if is_json { parse_json(&contents) } else { parse_lsl(&contents) };
+        settings.map_err(|e| format!("Unable to parse {}: {}", path.display(), e))
+    }
+}
+
+impl Layout {
+    pub fn new(timer: SharedTimer, settings: &LayoutSettings) -> Layout {
+        Layout {
+            timer: timer,
+            components: settings.components.iter().map(Component::new).collect(),
+        }
+    }
+
+    /* Limit every splits component to `rows` splits. Returns whether anything changed. */
+    pub fn fit_splits(&mut self, rows: usize) -> bool {
+        let mut changed = false;
+        for component in &mut self.components {
+            if let Component::Splits(ref mut splits) = *component {
+                changed |= splits.fit(rows);
+            }
+        }
+        changed
+    }
+
+    pub fn scroll_splits(&mut self, up: bool) {
+        for component in &mut self.components {
+            if let Component::Splits(ref mut splits) = *component {
+                if up {
+                    splits.component.scroll_up();
+                } else {
+                    splits.component.scroll_down();
+                }
+            }
+        }
+    }
+
+    pub fn states(&mut self, layout_settings: &GeneralSettings) -> Vec<ComponentState> {
+        let timer = self.timer.read();
+        self.components
+            .iter_mut()
+            .map(|c| c.state(&timer, layout_settings))
+            .collect()
+    }
+}
+
+impl Component {
+    fn new(settings: &ComponentSettings) -> Component {
+        match *settings {
+            ComponentSettings::Title(_) => Component::Title(title::Component::new()),
+            ComponentSettings::Splits(ref s) => Component::Splits(SplitsComponent::new(s)),
+            ComponentSettings::Timer(ref s) => {
+                Component::Timer(timer::Component::new(),
+                                 s.accuracy.unwrap_or_default(),
+                                 s.rows.unwrap_or(2))
+            }
+            ComponentSettings::PreviousSegment(ref s) => {
+                let component = previous_segment::Component::with_settings(previous_segment::Settings {
+                    comparison_override: s.comparison_override.clone(),
+                    ..Default::default()
+                });
+                Component::PreviousSegment(component, s.accuracy)
+            }
+            ComponentSettings::SumOfBest(ref s) => {
+                Component::SumOfBest(sum_of_best::Component::new(), s.accuracy)
+            }
+            ComponentSettings::PossibleTimeSave(ref s) => {
+                let component = possible_time_save::Component::with_settings(possible_time_save::Settings {
+                    comparison_override: s.comparison_override.clone(),
+                    ..Default::default()
+                });
+                Component::PossibleTimeSave(component, s.accuracy)
+            }
+            ComponentSettings::CurrentComparison(_) => {
+                Component::CurrentComparison(current_comparison::Component::new())
+            }
+            ComponentSettings::CurrentPace(ref s) => {
+                let component = current_pace::Component::with_settings(current_pace::Settings {
+                    comparison_override: s.comparison_override.clone(),
+                    ..Default::default()
+                });
+                Component::CurrentPace(component, s.accuracy)
+            }
+            ComponentSettings::Delta(ref s) => {
+                let component = delta::Component::with_settings(delta::Settings {
+                    comparison_override: s.comparison_override.clone(),
+                    ..Default::default()
+                });
+                Component::Delta(component, s.accuracy)
+            }
+            ComponentSettings::TotalPlaytime(_) => Component::TotalPlaytime(total_playtime::Component::new()),
+            ComponentSettings::DetailedTimer(ref s) => {
+                Component::DetailedTimer(detailed_timer::Component::new(),
+                                         s.accuracy.unwrap_or_default(),
+                                         s.rows.unwrap_or(1))
+            }
+            ComponentSettings::Text(ref s) => {
+                let text = match s.text {
+                    TextContent::Center(ref center) => text::Text::Center(center.clone()),
+                    TextContent::Split(ref left, ref right) => text::Text::Split(left.clone(), right.clone()),
+                };
+                Component::Text(text::Component::with_settings(text::Settings {
+                    text: text,
+                    ..Default::default()
+                }))
+            }
+            ComponentSettings::Separator(_) => Component::Separator(separator::Component::new()),
+            ComponentSettings::BlankSpace(ref s) => {
+                Component::BlankSpace(blank_space::Component::new(), s.rows.unwrap_or(1))
+            }
+            ComponentSettings::Graph(ref s) => {
+                let component = graph::Component::with_settings(graph::Settings {
+                    comparison_override: s.comparison_override.clone(),
+                    ..Default::default()
+                });
+                Component::Graph(component, s.rows.unwrap_or(6))
+            }
+        }
+    }
+
+    fn state(&mut self, timer: &Timer, layout_settings: &GeneralSettings) -> ComponentState {
+        match *self {
+            Component::Title(ref mut c) => ComponentState::Title(c.state(timer)),
+            Component::Splits(ref mut c) => ComponentState::Splits(c.component.state(timer, layout_settings)),
+            Component::Timer(ref mut c, accuracy, height) => {
+                let mut state = c.state(timer, layout_settings);
+                state.fraction = accuracy.apply(&state.fraction);
+                ComponentState::Timer(state, height)
+            }
+            Component::PreviousSegment(ref mut c, accuracy) => {
+                let mut state = c.state(timer, layout_settings);
+                state.time = accuracy.apply(&state.time);
+                ComponentState::PreviousSegment(state)
+            }
+            Component::SumOfBest(ref mut c, accuracy) => {
+                let mut state = c.state(timer);
+                state.time = accuracy.apply(&state.time);
+                ComponentState::SumOfBest(state)
+            }
+            Component::PossibleTimeSave(ref mut c, accuracy) => {
+                let mut state = c.state(timer);
+                state.time = accuracy.apply(&state.time);
+                ComponentState::PossibleTimeSave(state)
+            }
+            Component::CurrentComparison(ref mut c) => ComponentState::CurrentComparison(c.state(timer)),
+            Component::CurrentPace(ref mut c, accuracy) => {
+                let mut state = c.state(timer);
+                state.time = accuracy.apply(&state.time);
+                ComponentState::CurrentPace(state)
+            }
+            Component::Delta(ref mut c, accuracy) => {
+                let mut state = c.state(timer, layout_settings);
+                state.time = accuracy.apply(&state.time);
+                ComponentState::Delta(state)
+            }
+            Component::TotalPlaytime(ref mut c) => ComponentState::TotalPlaytime(c.state(timer)),
+            Component::DetailedTimer(ref mut c, accuracy, height) => {
+                let mut state = c.state(timer, layout_settings);
+                state.timer.fraction = accuracy.apply(&state.timer.fraction);
+                state.segment_timer.fraction = accuracy.apply(&state.segment_timer.fraction);
+                ComponentState::DetailedTimer(state, height)
+            }
+            Component::Text(ref mut c) => ComponentState::Text(c.state()),
+            Component::Separator(ref mut c) => ComponentState::Separator(c.state()),
+            Component::BlankSpace(ref mut c, height) => ComponentState::BlankSpace(c.state(), height),
+            Component::Graph(ref mut c, height) => ComponentState::Graph(c.state(timer, layout_settings), height),
+        }
+    }
+}
+
+impl SplitsComponent {
+    fn new(s: &SplitsSettings) -> SplitsComponent {
+        let mut settings = splits::Settings::default();
+        if let Some(count) = s.visual_split_count {
+            settings.visual_split_count = count;
+        }
+        if let Some(count) = s.split_preview_count {
+            settings.split_preview_count = count;
+        }
+        if let Some(always) = s.always_show_last_split {
+            settings.always_show_last_split = always;
+        }
+        if let Some(separator) = s.separator_last_split {
+            settings.separator_last_split = separator;
+        }
+
+        SplitsComponent {
+            component: splits::Component::with_settings(settings.clone()),
+            visual_split_count: settings.visual_split_count,
+            settings: settings,
+        }
+    }
+
+    /* Show at most `rows` splits, or as many as fit when the count is 0 */
+    fn fit(&mut self, rows: usize) -> bool {
+        let count = match self.settings.visual_split_count {
+            0 => rows,
+            configured => min(configured, rows),
+        };
+        let count = if count == 0 { 1 } else { count };
+
+        if count == self.visual_split_count {
+            return false;
+        }
+
+        let mut settings = self.settings.clone();
+        settings.visual_split_count = count;
+        self.component = splits::Component::with_settings(settings);
+        self.visual_split_count = count;
+        true
+    }
+}
+
+/* Build the component list from a LiveSplit .lsl file. Components we can't
+ * draw in a terminal are skipped. */
+fn parse_lsl(contents: &str) -> Result<LayoutSettings, String> {
+    let package = parser::parse(contents).map_err(|_| String::from("not a valid XML file"))?;
+    let document = package.as_document();
+    let layout = document.root()
+        .children()
+        .into_iter()
+        .filter_map(|c| c.element())
+        .find(|e| e.name().local_part() == "Layout")
+        .ok_or(String::from("not a LiveSplit layout"))?;
+    let components = child(layout, "Components").ok_or(String::from("no components"))?;
+
+    let mut settings = Vec::new();
+    for component in components.children().into_iter().filter_map(|c| c.element()) {
+        let path = child(component, "Path").map(text).unwrap_or_default();
+        let component_settings = child(component, "Settings");
+        let setting = |name: &str| component_settings.and_then(|s| child(s, name)).map(text);
+
+        let accuracy = setting("Accuracy")
+            .or(setting("TimerAccuracy"))
+            .and_then(|a| Accuracy::parse(&a));
+        let comparison_override = setting("Comparison")
+            .and_then(|c| if c == "Current Comparison" { None } else { Some(c) });
+        let info = InfoSettings {
+            comparison_override: comparison_override,
+            accuracy: accuracy.unwrap_or_default(),
+        };
+
+        settings.push(match path.as_str() {
+            "LiveSplit.Title.dll" => ComponentSettings::Title(NoSettings::default()),
+            /* Subsplits are drawn as plain splits, like livesplit-core does */
+            "LiveSplit.Splits.dll" | "LiveSplit.Subsplits.dll" => ComponentSettings::Splits(SplitsSettings {
+                visual_split_count: setting("VisualSplitCount").and_then(|c| c.parse().ok()),
+                split_preview_count: setting("SplitPreviewCount").and_then(|c| c.parse().ok()),
+                always_show_last_split: setting("AlwaysShowLastSplit").map(|a| a == "True"),
+                separator_last_split: setting("SeparatorLastSplit").map(|a| a == "True"),
+            }),
+            "LiveSplit.Timer.dll" => ComponentSettings::Timer(TimerSettings {
+                accuracy: accuracy,
+                rows: None,
+            }),
+            "LiveSplit.PreviousSegment.dll" => ComponentSettings::PreviousSegment(info),
+            "LiveSplit.SumOfBest.dll" => ComponentSettings::SumOfBest(info),
+            "LiveSplit.PossibleTimeSave.dll" => ComponentSettings::PossibleTimeSave(info),
+            "LiveSplit.CurrentComparison.dll" => ComponentSettings::CurrentComparison(NoSettings::default()),
+            "LiveSplit.RunPrediction.dll" => ComponentSettings::CurrentPace(info),
+            "LiveSplit.Delta.dll" => ComponentSettings::Delta(info),
+            "LiveSplit.TotalPlaytime.dll" => ComponentSettings::TotalPlaytime(NoSettings::default()),
+            "LiveSplit.DetailedTimer.dll" => ComponentSettings::DetailedTimer(TimerSettings {
+                accuracy: accuracy,
+                rows: None,
+            }),
+            "LiveSplit.Text.dll" => {
+                /* Centered unless there's something to show on the right */
+                let left = setting("Text1").unwrap_or_default();
+                let right = setting("Text2").unwrap_or_default();
+                let content = if right.is_empty() {
+                    TextContent::Center(left)
+                } else {
+                    TextContent::Split(left, right)
+                };
+                ComponentSettings::Text(TextSettings { text: content })
+            }
+            /* LiveSplit stores separators without a path */
+            "" => ComponentSettings::Separator(NoSettings::default()),
+            "LiveSplit.BlankSpace.dll" => ComponentSettings::BlankSpace(HeightSettings::default()),
+            "LiveSplit.Graph.dll" => ComponentSettings::Graph(GraphSettings {
+                comparison_override: info.comparison_override,
+                rows: None,
+            }),
+            _ => continue,
+        });
+    }
+
+    Ok(LayoutSettings { components: settings })
+}
+
+/* Build the component list from livesplit-core layout JSON. Components of
+ * types we can't draw are skipped, but the rest have to parse. */
+fn parse_json(contents: &str) -> Result<LayoutSettings, String> {
+    let layout: JsonLayout = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+
+    let mut settings = Vec::new();
+    for component in layout.components {
+        let known = component.as_object()
+            .map(|c| c.keys().all(|name| COMPONENT_NAMES.contains(&name.as_str())))
+            .unwrap_or(true);
+        if known {
+            settings.push(serde_json::from_value(component).map_err(|e| e.to_string())?);
+        }
+    }
+
+    Ok(LayoutSettings { components: settings })
+}
+
+fn child<'d>(element: Element<'d>, name: &str) -> Option<Element<'d>> {
+    element.children()
+        .into_iter()
+        .filter_map(|c| c.element())
+        .find(|e| e.name().local_part() == name)
+}
+
+fn text(element: Element) -> String {
+    element.children()
+        .into_iter()
+        .filter_map(|c| c.text())
+        .map(|t| t.text())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYOUT: &'static str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Layout version="1.6.1">
+  <Mode>Vertical</Mode>
+  <Components>
+    <Component>
+      <Path>LiveSplit.Title.dll</Path>
+      <Settings />
+    </Component>
+    <Component>
+      <Path>LiveSplit.Splits.dll</Path>
+      <Settings>
+        <VisualSplitCount>8</VisualSplitCount>
+        <AlwaysShowLastSplit>True</AlwaysShowLastSplit>
+      </Settings>
+    </Component>
+    <Component>
+      <Path>LiveSplit.PreviousSegment.dll</Path>
+      <Settings>
+        <Comparison>Best Segments</Comparison>
+        <TimerAccuracy>Tenths</TimerAccuracy>
+      </Settings>
+    </Component>
+    <Component>
+      <Path>LiveSplit.ScriptableAutoSplit.dll</Path>
+    </Component>
+    <Component>
+      <Path></Path>
+    </Component>
+    <Component>
+      <Path>LiveSplit.Text.dll</Path>
+      <Settings>
+        <Text1>Any%</Text1>
+        <Text2 />
+      </Settings>
+    </Component>
+    <Component>
+      <Path>LiveSplit.Subsplits.dll</Path>
+      <Settings>
+        <VisualSplitCount>12</VisualSplitCount>
+      </Settings>
+    </Component>
+  </Components>
+</Layout>"#;
+
+    #[test]
+    fn reads_lsl_components_in_order() {
+        let components = parse_lsl(LAYOUT).unwrap().components;
+        assert_eq!(components.len(), 6);
+
+        match components[0] {
+            ComponentSettings::Title(_) => {}
+            ref other => panic!("expected a title, got {:?}", other),
+        }
+        match components[1] {
+            ComponentSettings::Splits(ref s) => {
+                assert_eq!(s.visual_split_count, Some(8));
+                assert_eq!(s.always_show_last_split, Some(true));
+                assert_eq!(s.split_preview_count, None);
+            }
+            ref other => panic!("expected splits, got {:?}", other),
+        }
+        match components[2] {
+            ComponentSettings::PreviousSegment(ref s) => {
+                assert_eq!(s.comparison_override, Some(String::from("Best Segments")));
+                assert_eq!(s.accuracy, Accuracy::Tenths);
+            }
+            ref other => panic!("expected a previous segment, got {:?}", other),
+        }
+        match components[3] {
+            ComponentSettings::Separator(_) => {}
+            ref other => panic!("expected a separator, got {:?}", other),
+        }
+        match components[4] {
+            ComponentSettings::Text(TextSettings { text: TextContent::Center(ref text) }) => {
+                assert_eq!(text, "Any%")
+            }
+            ref other => panic!("expected centered text, got {:?}", other),
+        }
+        match components[5] {
+            ComponentSettings::Splits(ref s) => assert_eq!(s.visual_split_count, Some(12)),
+            ref other => panic!("expected subsplits as splits, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn current_comparison_is_no_override() {
+        let layout = LAYOUT.replace("Best Segments", "Current Comparison");
+        match parse_lsl(&layout).unwrap().components[2] {
+            ComponentSettings::PreviousSegment(ref s) => assert_eq!(s.comparison_override, None),
+            ref other => panic!("expected a previous segment, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(parse_lsl("not xml").is_err());
+        assert!(parse_lsl("<Run></Run>").is_err());
+    }
+
+    #[test]
+    fn reads_livesplit_core_json() {
+        let json = r#"{"components": [{"Title": {"background": "Transparent"}},
+                                      {"Timer": {"accuracy": "Seconds", "height": 60, "rows": 4}},
+                                      {"SegmentTime": {}},
+                                      {"Text": {"text": {"Split": ["Left", "Right"]}}},
+                                      {"Graph": {"height": 80}}],
+                       "general": {"direction": "Vertical"}}"#;
+        let components = parse_json(json).unwrap().components;
+        assert_eq!(components.len(), 4);
+        match components[1] {
+            ComponentSettings::Timer(ref s) => {
+                assert_eq!(s.accuracy, Some(Accuracy::Seconds));
+                assert_eq!(s.rows, Some(4));
+            }
+            ref other => panic!("expected a timer, got {:?}", other),
+        }
+        match components[3] {
+            ComponentSettings::Graph(ref s) => assert_eq!(s.rows, None),
+            ref other => panic!("expected a graph, got {:?}", other),
+        }
+        match components[2] {
+            ComponentSettings::Text(TextSettings { text: TextContent::Split(ref left, ref right) }) => {
+                assert_eq!((left.as_str(), right.as_str()), ("Left", "Right"))
+            }
+            ref other => panic!("expected split text, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_bad_settings_of_known_components() {
+        assert!(parse_json(r#"{"components": [{"Timer": {"accuracy": "Minutes"}}]}"#).is_err());
+        assert!(parse_json(r#"{"general": {}}"#).is_err());
+    }
+}
@@ -1,52 +1,77 @@
+extern crate chrono;
+extern crate libc;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 extern crate structopt;
 #[macro_use]
 extern crate structopt_derive;
 extern crate livesplit_core;
+extern crate sxd_document;
 extern crate termion;
+extern crate toml;
 extern crate tui;
-
-use livesplit_core::{Timer, Run, Segment, HotkeySystem, SharedTimer};
-use livesplit_core::run::parser::composite;
+extern crate unicode_width;
+
+mod big_digits;
+mod commands;
+mod config;
+mod delta_graph;
+mod editor;
+mod export;
+mod guard;
+mod headless;
+mod history;
+mod keymap;
+mod layout;
+mod render;
+mod run_file;
+mod server;
+mod stats;
+mod time_format;
+
+use livesplit_core::{Timer, TimerPhase, TimingMethod, Run, Segment, HotkeySystem, SharedTimer};
 use livesplit_core::layout::{GeneralSettings};
-use livesplit_core::component::{timer, splits, title, previous_segment, sum_of_best,
-                                possible_time_save};
 use std::{thread, io};
-use std::io::BufReader;
-use std::time::Duration;
-use std::sync::mpsc::channel;
-use std::fs::File;
+use std::cmp::{min, max};
+use std::time::{Duration, Instant};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::path::Path;
 use structopt::StructOpt;
-use termion::event::Key;
+use termion::event::{Event as InputEvent, Key, MouseButton, MouseEvent};
 use termion::input::TermRead;
 use tui::Terminal;
 use tui::backend::TermionBackend;
-use tui::layout::{Group, Direction, Size};
-use tui::widgets::{Table, Widget, Paragraph};
-use tui::style::{Color, Style, Modifier};
-
-struct Layout {
-    timer: SharedTimer,
-    components: Components,
-}
-
-struct Components {
-    timer: timer::Component,
-    splits: splits::Component,
-    title: title::Component,
-    previous_segment: previous_segment::Component,
-    sum_of_best: sum_of_best::Component,
-    possible_time_save: possible_time_save::Component,
+use config::{Config, ResetConfirm};
+use editor::{Editor, Outcome};
+use guard::{Signal, TerminalGuard};
+use history::{AttemptHistory, SegmentHistory};
+use keymap::{Action, Keymap};
+use layout::{Layout, LayoutSettings};
+use render::{draw, draw_attempt_history, draw_editor, draw_segment_history, target_at, Overlay, Target};
+use run_file::RunFile;
+use server::Request;
+use time_format::parse_time;
+
+/* Everything the main loop reacts to */
+pub enum Event {
+    Key(Key),
+    Mouse(MouseEvent),
+    Command(Request),
+    Signal(Signal),
+    /* A thread other than the main one panicked, with this message */
+    Panic(String),
+    Quit,
 }
 
-/* Convert Livesplit display color to a tui color */
-fn get_tui_color(color: livesplit_core::settings::Color) -> tui::style::Color {
-    return Color::Rgb((color.rgba.red * 255.0) as u8,
-                      (color.rgba.green * 255.0) as u8,
-                      (color.rgba.blue * 255.0) as u8);
-}
+/* How often to redraw while the timer is running, and how often to check
+ * whether a global hotkey changed anything otherwise */
+const FRAME_INTERVAL: u64 = 33;
+const IDLE_INTERVAL: u64 = 100;
 
 /* Print an error and exit */
-fn error_out(error: &String) {
+fn error_out(error: &String) -> ! {
     print!("{}\n", error);
     std::process::exit(1);
 }
@@ -61,27 +86,153 @@ fn error_out(error: &String) {
 struct Opt {
     #[structopt(help = "Run file to load")]
     run_file: Option<String>,
+
+    #[structopt(long = "layout", help = "LiveSplit .lsl layout file, or livesplit-core layout JSON ending in .json")]
+    layout: Option<String>,
+
+    #[structopt(long = "edit", help = "Open the run in the editor")]
+    edit: bool,
+
+    #[structopt(long = "mouse", help = "Scroll and click the splits, and show buttons to split, undo, skip and reset")]
+    mouse: bool,
+
+    #[structopt(long = "keymap", help = "Key bindings file overriding the config file (TOML, or JSON ending in .json)")]
+    keymap: Option<String>,
+
+    #[structopt(long = "server", help = "Accept LiveSplit Server commands on this TCP address, e.g. 127.0.0.1:16834")]
+    server: Option<String>,
+
+    #[structopt(long = "server-socket", help = "Accept LiveSplit Server commands on this Unix socket")]
+    server_socket: Option<String>,
+
+    #[structopt(long = "headless", help = "Run without the terminal interface, reading LiveSplit Server commands from stdin")]
+    headless: bool,
+
+    #[structopt(long = "json", help = "In headless mode, write the component states to stdout as JSON lines")]
+    json: bool,
+
+    #[structopt(long = "interval", help = "Milliseconds between JSON states in headless mode", default_value = "100")]
+    interval: u64,
+
+    #[structopt(long = "practice", help = "Start in practice mode, where attempts are only kept in the splits when chosen")]
+    practice: bool,
+
+    #[structopt(long = "backups", help = "Number of backups of the run file to keep when saving", default_value = "5")]
+    backups: usize,
+
+    #[structopt(long = "list-backups", help = "List backups of the run file and exit")]
+    list_backups: bool,
+
+    #[structopt(long = "restore-backup", help = "Restore the run file from a backup, given its name or timestamp, and exit")]
+    restore_backup: Option<String>,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+/* Subcommands that work on splits files without starting the timer */
+#[derive(StructOpt, Debug)]
+enum Command {
+    #[structopt(name = "new", about = "Create a splits file")]
+    New {
+        #[structopt(help = "Splits file to create")]
+        output: String,
+
+        #[structopt(long = "game", help = "Game name", default_value = "")]
+        game: String,
+
+        #[structopt(long = "category", help = "Category name", default_value = "Any%")]
+        category: String,
+
+        #[structopt(long = "segments", help = "Comma separated segment names, otherwise one per line on stdin")]
+        segments: Option<String>,
+
+        #[structopt(long = "force", help = "Overwrite an existing file")]
+        force: bool,
+    },
+
+    #[structopt(name = "convert", about = "Convert splits from any supported format")]
+    Convert {
+        #[structopt(help = "Splits file to read (LiveSplit, Llanfair, WSplit, Urn, SplitterZ, ...)")]
+        input: String,
+
+        #[structopt(help = "File to write")]
+        output: String,
+
+        #[structopt(long = "format", help = "Output format: lss, urn or csv. Guessed from the output file name if left out")]
+        format: Option<String>,
+    },
+
+    #[structopt(name = "stats", about = "Print attempts, segment times, golds and the sum of best")]
+    Stats {
+        #[structopt(help = "Splits file to read")]
+        input: String,
+
+        #[structopt(long = "format", help = "Output format: csv or json", default_value = "csv")]
+        format: String,
+    },
 }
 
 fn main() {
     let opt = Opt::from_args();
 
+    if let Some(ref command) = opt.command {
+        let result = match *command {
+            Command::New { ref output, ref game, ref category, ref segments, force } => {
+                commands::new_run(output, game, category, segments.as_ref().map(|s| s.as_str()), force)
+            }
+            Command::Convert { ref input, ref output, ref format } => {
+                commands::convert(input, output, format.as_ref().map(|s| s.as_str()))
+            }
+            Command::Stats { ref input, ref format } => commands::stats(input, format),
+        };
+        if let Err(error) = result {
+            error_out(&error);
+        }
+        return;
+    }
+
+    /* Manage backups instead of starting the timer */
+    if opt.list_backups || opt.restore_backup != None {
+        let splits_filename = match opt.run_file {
+            Some(ref filename) => filename,
+            None => error_out(&String::from("A run file is required to manage backups")),
+        };
+        let path = Path::new(splits_filename);
+
+        if let Some(ref backup) = opt.restore_backup {
+            match run_file::restore_backup(path, backup, opt.backups) {
+                Ok(source) => print!("Restored {} from {}\n", splits_filename, source.display()),
+                Err(error) => error_out(&error),
+            }
+        }
+
+        if opt.list_backups {
+            match run_file::list_backups(path) {
+                Ok(backups) => for backup in backups {
+                    print!("{}\n", backup.display());
+                },
+                Err(error) => error_out(&format!("Unable to list backups of {}: {}", splits_filename, error)),
+            }
+        }
+        return;
+    }
+
+    /* Load key bindings */
+    let mut config = Config::load().unwrap_or_else(|error| error_out(&error));
+    if let Some(ref keymap_file) = opt.keymap {
+        if let Err(error) = config.merge_keymap(Path::new(keymap_file)) {
+            error_out(&error);
+        }
+    }
+    let keymap = Keymap::new(&config.keys).unwrap_or_else(|error| error_out(&error));
+
     /* Open Run if we can, otherwise default */
     let mut run = Run::new();
     if opt.run_file != None {
-         let ref splits_filename = opt.run_file.unwrap();
-
-         let file_result = File::open(splits_filename);
-         if file_result.is_err() {
-             error_out(&format!("Unable to open {}", splits_filename));
-         }
-         let file = file_result.unwrap();
-
-         let run_result = composite::parse(BufReader::new(file), None, true);
-         if run_result.is_err() {
-             error_out(&format!("Unable to parse {}", splits_filename));
-         }
-         run = run_result.unwrap();
+         let ref splits_filename = opt.run_file.clone().unwrap();
+
+         run = run_file::load(splits_filename).unwrap_or_else(|error| error_out(&error));
     } else {
         run.set_game_name("Livesplit Terminal");
         run.set_category_name("Any%");
@@ -96,150 +247,580 @@ fn main() {
     /* Create Livesplit things */
     let timer = Timer::new(run).unwrap().into_shared();
     let _hotkey_system = HotkeySystem::new(timer.clone()).ok();
-    let mut layout = Layout {
-        timer: timer.clone(),
-        components: Components {
-            timer: timer::Component::new(),
-            splits: splits::Component::new(),
-            title: title::Component::new(),
-            previous_segment: previous_segment::Component::new(),
-            sum_of_best: sum_of_best::Component::new(),
-            possible_time_save: possible_time_save::Component::new(),
-        },
-    };
-    let mut layout_settings = GeneralSettings::default();
+    let layout_file = match opt.layout {
+        Some(ref layout_filename) => {
+            LayoutSettings::load(Path::new(layout_filename)).unwrap_or_else(|error| error_out(&error))
+        }
+        None => LayoutSettings::default(),
+    }.with_defaults(&config.splits, &config.timer);
+    let mut layout = Layout::new(timer.clone(), &layout_file);
+    let layout_settings = GeneralSettings::default();
+
+    let mut run_file = RunFile::new(opt.run_file, opt.backups);
+    if opt.practice {
+        run_file.start_practice(timer.read().run().clone());
+    }
+
+    let (tx, rx) = channel();
+
+    /* Start control servers */
+    if let Some(ref address) = opt.server {
+        if let Err(error) = server::listen_tcp(address, tx.clone()) {
+            error_out(&format!("Unable to listen on {}: {}", address, error));
+        }
+    }
+    if let Some(ref path) = opt.server_socket {
+        if let Err(error) = server::listen_unix(path, tx.clone()) {
+            error_out(&format!("Unable to listen on {}: {}", path, error));
+        }
+    }
+
+    if opt.headless {
+        if let Err(error) = guard::forward_signals(tx.clone(), false) {
+            error_out(&format!("Unable to handle signals: {}", error));
+        }
+        let interval = Duration::from_millis(opt.interval);
+        if let Err(error) = headless::run(timer, layout, &layout_settings, run_file, tx, rx, opt.json, interval) {
+            error_out(&error);
+        }
+        return;
+    }
 
-    /* Create tui things */
+    /* Create tui things, making sure the terminal gets put back afterwards */
+    let original_attributes = guard::attributes()
+        .unwrap_or_else(|error| error_out(&format!("Unable to read terminal settings: {}", error)));
     let mut terminal = Terminal::new(TermionBackend::new().unwrap()).unwrap();
+    let guard = TerminalGuard::new(original_attributes, opt.mouse, tx.clone()).unwrap();
     terminal.clear().unwrap();
     terminal.hide_cursor().unwrap();
+    guard::forward_signals(tx.clone(), true).unwrap();
 
-    /* Spawn IO thread */
-    let (tx, rx) = channel();
-
+    /* Spawn IO thread. Running out of input quits, and failing to read it is
+     * handled like a panic. */
+    let input = tx.clone();
     thread::spawn(move || {
-        loop {
-            let stdin = io::stdin();
-            for key in stdin.keys() {
-                let c = key.unwrap();
-                match c {
-                    Key::Char('q') => break,
-                    Key::Char('1') => timer.write().split_or_start(),
-                    Key::Char('2') => timer.write().skip_split(),
-                    Key::Char('3') => timer.write().reset(true),
-                    Key::Char('4') => timer.write().switch_to_previous_comparison(),
-                    Key::Char('5') => timer.write().toggle_pause_or_start(),
-                    Key::Char('6') => timer.write().switch_to_next_comparison(),
-                    Key::Char('8') => timer.write().undo_split(),
-                    _ => {}
+        let stdin = io::stdin();
+        for event in stdin.events() {
+            let event = match event {
+                Ok(InputEvent::Key(key)) => Event::Key(key),
+                Ok(InputEvent::Mouse(mouse)) => Event::Mouse(mouse),
+                Ok(InputEvent::Unsupported(_)) => continue,
+                Err(error) => {
+                    let _ = input.send(Event::Panic(format!("Unable to read input: {}", error)));
+                    return;
                 }
+            };
+            if input.send(event).is_err() {
+                return;
             }
-            tx.send(()).unwrap();
         }
+        let _ = input.send(Event::Quit);
     });
 
-    /* Read data from IO thread while continuously updating display */
-    loop {
-        if let Ok(_) = rx.try_recv() {
-            break;
+    let mut overlay = None;
+    let mut editor = if opt.edit {
+        Some(Editor::new(timer.read().run().clone()))
+    } else {
+        None
+    };
+    let mut history: Option<SegmentHistory> = None;
+    let mut attempt_history: Option<AttemptHistory> = None;
+    let mut last_size = terminal.size().unwrap();
+    let mut last_reset_press: Option<Instant> = None;
+    let mut targets = Vec::new();
+    let mut panicked = None;
+
+    /* Sleep until an event arrives, only ticking at the full frame rate while
+     * the time on screen is moving */
+    let mut redraw = true;
+    let mut drawn: Option<Snapshot> = None;
+    let mut seen = Snapshot::take(&timer);
+    let reset_window = Duration::from_millis(config.reset.double_press_ms);
+    'main: loop {
+        let timeout = Duration::from_millis(match timer.read().current_phase() {
+            TimerPhase::Running => FRAME_INTERVAL,
+            _ => IDLE_INTERVAL,
+        });
+        /* Wake up to take the reset hint down once the second press is too late */
+        let timeout = match last_reset_press {
+            Some(pressed) if pressed.elapsed() < reset_window => min(timeout, reset_window - pressed.elapsed()),
+            _ => timeout,
+        };
+        let first = match rx.recv_timeout(timeout) {
+            Ok(event) => Some(event),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => break 'main,
+        };
+        if last_reset_press.map(|pressed| pressed.elapsed() >= reset_window).unwrap_or(false) {
+            last_reset_press = None;
+            redraw = true;
         }
+        catch_up_with_hotkeys(&timer, &mut run_file, &seen);
+
+        for event in first.into_iter().chain(rx.try_iter()) {
+            redraw = true;
+            let action = match event {
+                Event::Command(request) => {
+                    let response = request.command.execute(&timer, &mut run_file);
+                    let _ = request.reply.send(response);
+                    continue;
+                }
+                Event::Signal(Signal::Interrupt) |
+                Event::Signal(Signal::Terminate) |
+                Event::Signal(Signal::HangUp) |
+                Event::Quit => break 'main,
+                Event::Panic(message) => {
+                    panicked = Some(message);
+                    break 'main;
+                }
+                Event::Signal(Signal::Suspend) => {
+                    guard.suspend();
+                    full_redraw(&mut terminal);
+                    continue;
+                }
+                Event::Signal(Signal::Continue) => {
+                    guard.resume();
+                    full_redraw(&mut terminal);
+                    continue;
+                }
+                /* Picked up by the size check below */
+                Event::Signal(Signal::Resize) => continue,
+                Event::Mouse(mouse) => {
+                    /* The editor, history views and dialogs only take keys */
+                    if editor.is_some() || history.is_some() || attempt_history.is_some() || overlay.is_some() {
+                        continue;
+                    }
+                    match mouse {
+                        MouseEvent::Press(MouseButton::WheelUp, _, _) => {
+                            layout.scroll_splits(true);
+                            continue;
+                        }
+                        MouseEvent::Press(MouseButton::WheelDown, _, _) => {
+                            layout.scroll_splits(false);
+                            continue;
+                        }
+                        /* termion counts from 1, though some terminals send 0 */
+                        MouseEvent::Press(MouseButton::Left, x, y) => {
+                            match target_at(&targets, x.saturating_sub(1), y.saturating_sub(1)) {
+                                Some(&Target::Button(action)) => action,
+                                Some(&Target::Split(row, rows)) => {
+                                    /* Scroll the clicked split to the middle of the list */
+                                    for _ in rows / 2..row {
+                                        layout.scroll_splits(false);
+                                    }
+                                    for _ in row..rows / 2 {
+                                        layout.scroll_splits(true);
+                                    }
+                                    continue;
+                                }
+                                None => continue,
+                            }
+                        }
+                        _ => continue,
+                    }
+                }
+                Event::Key(key) => {
+                    /* Raw mode turns Ctrl-C and Ctrl-Z into plain keys */
+                    match key {
+                        Key::Ctrl('c') => break 'main,
+                        Key::Ctrl('z') => {
+                            guard.suspend();
+                            full_redraw(&mut terminal);
+                            continue;
+                        }
+                        _ => {}
+                    }
+
+                    /* The editor takes every key while it's open */
+                    let outcome = match editor {
+                        Some(ref mut editor) => editor.handle_key(key),
+                        None => Outcome::Continue,
+                    };
+                    match outcome {
+                        Outcome::Save => {
+                            /* A hotkey or server command may have started the timer meanwhile */
+                            if timer.read().current_phase() != TimerPhase::NotRunning {
+                                editor.as_mut().unwrap().set_error(String::from("Reset the timer before saving"));
+                                continue;
+                            }
+                            let run = editor.take().unwrap().close();
+                            let result = timer.write().set_run(run);
+                            match result {
+                                Ok(_) => {
+                                    run_file.commit(timer.read().run());
+                                    /* Keep editing if the file couldn't be written, so saving
+                                     * can be tried again. Without a file there's nothing to
+                                     * retry, and the error is shown below the layout. */
+                                    if let Err(error) = run_file.save(timer.read().run()) {
+                                        if run_file.has_file() {
+                                            editor = Some(Editor::reopen(timer.read().run().clone(), error));
+                                        }
+                                    }
+                                }
+                                Err(run) => {
+                                    editor = Some(Editor::reopen(run, String::from("Unable to use the edited run")));
+                                }
+                            }
+                            continue;
+                        }
+                        Outcome::Discard => {
+                            editor = None;
+                            continue;
+                        }
+                        Outcome::Continue if editor.is_some() => continue,
+                        Outcome::Continue => {}
+                    }
+
+                    /* So do the history views */
+                    if let Some(mut view) = history.take() {
+                        let stats = {
+                            let timer = timer.read();
+                            stats::segment_stats(timer.run(), timer.current_timing_method())
+                        };
+                        if view.handle_key(key, &stats) {
+                            history = Some(view);
+                        }
+                        continue;
+                    }
+                    if let Some(mut view) = attempt_history.take() {
+                        let stats = {
+                            let timer = timer.read();
+                            stats::attempt_stats(timer.run(), timer.current_timing_method())
+                        };
+                        if view.handle_key(key, &stats) {
+                            attempt_history = Some(view);
+                        }
+                        continue;
+                    }
+
+                    /* An open dialog takes every key */
+                    let close_overlay = match overlay {
+                        Some(Overlay::ConfirmReset) => {
+                            match key {
+                                Key::Char('y') => reset(&timer, &mut run_file, true),
+                                Key::Char('n') => reset(&timer, &mut run_file, false),
+                                Key::Char('c') | Key::Esc => {}
+                                _ => continue,
+                            }
+                            true
+                        }
+                        Some(Overlay::LoadingTimes(ref mut text)) => {
+                            match key {
+                                Key::Char('\n') => {
+                                    /* Keep the dialog open until the time parses */
+                                    match parse_time(text) {
+                                        Some(time) => {
+                                            initialize_game_time(&timer);
+                                            timer.write().set_loading_times(time);
+                                            true
+                                        }
+                                        None => false,
+                                    }
+                                }
+                                Key::Char(c) => {
+                                    text.push(c);
+                                    false
+                                }
+                                Key::Backspace => {
+                                    text.pop();
+                                    false
+                                }
+                                Key::Esc => true,
+                                _ => false,
+                            }
+                        }
+                        Some(Overlay::Comparisons(ref mut selected)) => {
+                            let comparisons = timer.read()
+                                .run()
+                                .comparisons()
+                                .map(String::from)
+                                .collect::<Vec<_>>();
+                            let picked = match key {
+                                Key::Up => {
+                                    *selected = selected.saturating_sub(1);
+                                    None
+                                }
+                                Key::Down => {
+                                    *selected = min(*selected + 1, comparisons.len().saturating_sub(1));
+                                    None
+                                }
+                                Key::Char('\n') => Some(*selected),
+                                Key::Char(c) if c >= '1' && c <= '9' => Some(c as usize - '1' as usize),
+                                _ => None,
+                            };
+                            match picked.and_then(|i| comparisons.get(i)) {
+                                Some(name) => {
+                                    let _ = timer.write().set_current_comparison(name.clone());
+                                    true
+                                }
+                                None => key == Key::Esc,
+                            }
+                        }
+                        None => false,
+                    };
+                    if overlay.is_some() {
+                        if close_overlay {
+                            overlay = None;
+                        }
+                        continue;
+                    }
+
+                    match keymap.action(key) {
+                        Some(action) => action,
+                        None => continue,
+                    }
+                }
+            };
 
-        draw(&mut terminal, &mut layout, &mut layout_settings);
-        thread::sleep(Duration::from_millis(33));
+            match action {
+                Action::Quit => break 'main,
+                Action::SplitOrStart => {
+                    timer.write().split_or_start();
+                    run_file.mark_modified();
+                }
+                Action::SkipSplit => {
+                    timer.write().skip_split();
+                    run_file.mark_modified();
+                }
+                Action::Reset => {
+                    if timer.read().current_phase() == TimerPhase::NotRunning {
+                        continue;
+                    }
+
+                    match config.reset.confirm {
+                        /* Keeping a practice attempt always has to be chosen */
+                        _ if run_file.is_practicing() => overlay = Some(Overlay::ConfirmReset),
+                        ResetConfirm::Prompt => overlay = Some(Overlay::ConfirmReset),
+                        ResetConfirm::DoublePress => {
+                            match last_reset_press {
+                                Some(pressed) if pressed.elapsed() < reset_window => {
+                                    reset(&timer, &mut run_file, true);
+                                    last_reset_press = None;
+                                }
+                                _ => last_reset_press = Some(Instant::now()),
+                            }
+                        }
+                        ResetConfirm::None => reset(&timer, &mut run_file, true),
+                    }
+                }
+                Action::PreviousComparison => timer.write().switch_to_previous_comparison(),
+                Action::TogglePauseOrStart => {
+                    timer.write().toggle_pause_or_start();
+                    run_file.mark_modified();
+                }
+                Action::NextComparison => timer.write().switch_to_next_comparison(),
+                Action::PickComparison => {
+                    /* Start from the comparison in use */
+                    let timer = timer.read();
+                    let selected = timer.run()
+                        .comparisons()
+                        .position(|c| c == timer.current_comparison())
+                        .unwrap_or(0);
+                    overlay = Some(Overlay::Comparisons(selected));
+                }
+                Action::UndoSplit => {
+                    timer.write().undo_split();
+                    run_file.mark_modified();
+                }
+                Action::ToggleTimingMethod => {
+                    let method = match timer.read().current_timing_method() {
+                        TimingMethod::RealTime => TimingMethod::GameTime,
+                        TimingMethod::GameTime => TimingMethod::RealTime,
+                    };
+                    timer.write().set_current_timing_method(method);
+                }
+                Action::ToggleGameTimePause => {
+                    initialize_game_time(&timer);
+                    let paused = timer.read().is_game_time_paused();
+                    if paused {
+                        timer.write().resume_game_time();
+                    } else {
+                        timer.write().pause_game_time();
+                    }
+                }
+                Action::SetLoadingTimes => overlay = Some(Overlay::LoadingTimes(String::new())),
+                Action::Edit => {
+                    /* The run can only be swapped out while the timer is stopped */
+                    if timer.read().current_phase() == TimerPhase::NotRunning {
+                        editor = Some(Editor::new(timer.read().run().clone()));
+                    }
+                }
+                Action::SegmentHistory => {
+                    /* Start from the segment being run */
+                    let timer = timer.read();
+                    let last = timer.run().segments().len().saturating_sub(1);
+                    let segment = min(max(timer.current_split_index(), 0) as usize, last);
+                    history = Some(SegmentHistory::new(segment));
+                }
+                Action::AttemptHistory => attempt_history = Some(AttemptHistory::new()),
+                Action::TogglePractice => {
+                    /* Only between attempts, so practice starts from the run as saved */
+                    if timer.read().current_phase() == TimerPhase::NotRunning {
+                        if run_file.is_practicing() {
+                            run_file.stop_practice();
+                        } else {
+                            run_file.start_practice(timer.read().run().clone());
+                        }
+                    }
+                }
+                Action::ScrollUp => layout.scroll_splits(true),
+                Action::ScrollDown => layout.scroll_splits(false),
+                /* A failure is shown until a save succeeds */
+                Action::Save => {
+                    let _ = run_file.save(timer.read().run());
+                }
+            }
+        }
+        seen = Snapshot::take(&timer);
+
+        /* Lay everything out again when the terminal changes size */
+        let size = terminal.size().unwrap();
+        if size != last_size {
+            full_redraw(&mut terminal);
+            last_size = size;
+            redraw = true;
+        }
+
+        /* Global hotkeys change the timer behind our back, so a running timer
+         * or any change to what's shown of it always redraws. tui only writes
+         * the cells that changed since the last frame. */
+        let snapshot = seen.clone();
+        if redraw || snapshot.phase == TimerPhase::Running || drawn.as_ref() != Some(&snapshot) {
+            match editor {
+                Some(ref mut editor) => {
+                    draw_editor(&mut terminal, editor);
+                    targets.clear();
+                }
+                None if history.is_some() => {
+                    let stats = {
+                        let timer = timer.read();
+                        stats::segment_stats(timer.run(), timer.current_timing_method())
+                    };
+                    draw_segment_history(&mut terminal, history.as_ref().unwrap(), &stats);
+                    targets.clear();
+                }
+                None if attempt_history.is_some() => {
+                    let stats = {
+                        let timer = timer.read();
+                        stats::attempt_stats(timer.run(), timer.current_timing_method())
+                    };
+                    draw_attempt_history(&mut terminal, attempt_history.as_ref().unwrap(), &stats);
+                    targets.clear();
+                }
+                None => {
+                    let hint = last_reset_press.map(|_| "Press reset again to confirm");
+                    targets = draw(&mut terminal, &mut layout, &layout_settings, &run_file, &overlay, hint, opt.mouse);
+                }
+            }
+            redraw = false;
+            drawn = Some(snapshot);
+        }
     }
 
-    terminal.clear().unwrap();
-    terminal.show_cursor().unwrap();
+    /* Save on the way out, reporting failure once the terminal is restored */
+    let save_result = if run_file.is_modified() {
+        run_file.save(timer.read().run())
+    } else {
+        Ok(())
+    };
+
+    guard.restore();
+
+    if let Some(message) = panicked {
+        print!("{}\n", message);
+        if let Err(error) = save_result {
+            print!("{}\n", error);
+        }
+        std::process::exit(101);
+    }
+    if let Err(error) = save_result {
+        error_out(&error);
+    }
 }
 
-/* Update display */
-fn draw(t: &mut Terminal<TermionBackend>, layout: &mut Layout, layout_settings: &mut GeneralSettings) {
-    let size = t.size().unwrap();
-
-    let splits_state = layout.components.splits.state(&layout.timer.read(), layout_settings);
-
-    Group::default()
-        .margin(1)
-        .sizes(&[Size::Fixed(3),
-                 Size::Fixed(splits_state.splits.len() as u16 + 3),
-                 Size::Fixed(2),
-                 Size::Fixed(1),
-                 Size::Fixed(1),
-                 Size::Fixed(1)])
-        .direction(Direction::Vertical)
-        .render(t, &size, |t, chunks| {
-            let state = layout.components.title.state(&layout.timer.read());
-
-            let category = format!("{:^35}", match state.line2 { None => String::new(), _ => state.line2.unwrap() });
-            let attempts = format!("{:>35}", state.attempts.unwrap());
-            let category: String = category.chars()
-                .zip(attempts.chars())
-                .map(|(c, a)| if a.is_whitespace() { c } else { a })
-                .collect();
-
-            Paragraph::default()
-                .text(&format!("{:^35}\n{}", state.line1, category))
-                .render(t, &chunks[0]);
-
-            let styles = splits_state.splits
-                .iter()
-                .map(|s| Style::default().fg(get_tui_color(s.semantic_color.visualize(layout_settings))))
-                .collect::<Vec<_>>();
-
-            let splits = splits_state.splits
-                .iter()
-                .zip(styles.iter())
-                .map(|(s, style)| {
-                    ([s.name.clone(), format!("{:>9}", s.delta), format!("{:>9}", s.time)], style)
-                })
-                .collect::<Vec<_>>();
-
-            Table::default()
-                .header(&["Split", "    Delta", "     Time"])
-                .header_style(Style::default().fg(Color::White))
-                .widths(&[15, 9, 9])
-                .style(Style::default().fg(Color::White))
-                .column_spacing(1)
-                .rows(&splits)
-                .render(t, &chunks[1]);
-
-            let state = layout.components.timer.state(&layout.timer.read(), layout_settings);
-
-            Paragraph::default()
-                .text(&format!("{:>32}{}", state.time, state.fraction))
-                .style(Style::default().modifier(Modifier::Bold).fg(get_tui_color(state.semantic_color.visualize(layout_settings))))
-                .render(t, &chunks[2]);
-
-            let state = layout.components.previous_segment.state(&layout.timer.read(), layout_settings);
-
-            Paragraph::default()
-                .text(&format_info_text(&state.text, &state.time))
-                .style(Style::default().fg(get_tui_color(state.semantic_color.visualize(layout_settings))))
-                .render(t, &chunks[3]);
-
-            let state = layout.components.sum_of_best.state(&layout.timer.read());
-
-            Paragraph::default()
-                .text(&format_info_text(&state.text, &state.time))
-                .style(Style::default().fg(Color::White))
-                .render(t, &chunks[4]);
-
-            let state = layout.components.possible_time_save.state(&layout.timer.read());
-
-            Paragraph::default()
-                .text(&format_info_text(&state.text, &state.time))
-                .style(Style::default().fg(Color::White))
-                .render(t, &chunks[5]);
-        });
+/* What global hotkeys can change while the timer isn't running. Cheap enough
+ * to take on every wake, unlike drawing. */
+#[derive(PartialEq, Clone)]
+struct Snapshot {
+    phase: TimerPhase,
+    split_index: isize,
+    comparison: String,
+    timing_method: TimingMethod,
+    game_time_paused: bool,
+    attempts: u32,
+}
+
+impl Snapshot {
+    fn take(timer: &SharedTimer) -> Snapshot {
+        let timer = timer.read();
+        Snapshot {
+            phase: timer.current_phase(),
+            split_index: timer.current_split_index(),
+            comparison: timer.current_comparison().to_string(),
+            timing_method: timer.current_timing_method(),
+            game_time_paused: timer.is_game_time_paused(),
+            attempts: timer.run().attempt_count(),
+        }
+    }
+
+    /* Whether the attempt itself moved on, rather than just how it's shown */
+    fn attempt_changed(&self, other: &Snapshot) -> bool {
+        self.phase != other.phase || self.split_index != other.split_index || self.attempts != other.attempts
+    }
+}
 
-    t.draw().unwrap();
+/* Global hotkeys drive the timer directly, so RunFile never hears of them.
+ * Whatever they did since `seen` is treated like the same key pressed here:
+ * changes to the attempt mark the run as modified, and resets are saved, or
+ * thrown away while practicing. Returns the timer as it is afterwards. */
+fn catch_up_with_hotkeys(timer: &SharedTimer, run_file: &mut RunFile, seen: &Snapshot) -> Snapshot {
+    let now = Snapshot::take(timer);
+    if now.phase == TimerPhase::NotRunning && seen.phase != TimerPhase::NotRunning {
+        /* The hotkey system always resets with the splits updated. While
+         * practicing, that's undone by going back to the practice run. */
+        let update_splits = !run_file.is_practicing();
+        after_reset(timer, run_file, update_splits);
+        return Snapshot::take(timer);
+    }
+    if now.attempt_changed(seen) {
+        run_file.mark_modified();
+    }
+    now
 }
 
-fn format_info_text(text: &str, value: &str) -> String {
-    let text = format!("{:<35}", text);
-    let value = format!("{:>35}", value);
-    text.chars()
-        .zip(value.chars())
-        .map(|(t, v)| if v.is_whitespace() { t } else { v })
-        .collect()
+/* Throw away what tui thinks is on screen, at the terminal's current size,
+ * and clear it so the next draw starts from nothing */
+fn full_redraw(terminal: &mut Terminal<TermionBackend>) {
+    if let Ok(size) = terminal.size() {
+        let _ = terminal.resize(size);
+    }
+    let _ = terminal.clear();
+}
+
+/* livesplit-core only keeps game time once it's been initialized, which every
+ * start undoes. Without an autosplitter or server client to do it, it's done
+ * here before game time is first paused or given loading times. */
+fn initialize_game_time(timer: &SharedTimer) {
+    let mut timer = timer.write();
+    if !timer.is_game_time_initialized() {
+        timer.initialize_game_time();
+    }
+}
+
+/* Reset the timer, saving the run if the attempt was committed to it. Practice
+ * attempts that aren't committed leave no trace, not even in the attempt count. */
+fn reset(timer: &SharedTimer, run_file: &mut RunFile, update_splits: bool) {
+    timer.write().reset(update_splits);
+    after_reset(timer, run_file, update_splits);
+}
+
+/* Everything a reset involves besides resetting the timer itself */
+fn after_reset(timer: &SharedTimer, run_file: &mut RunFile, update_splits: bool) {
+    if update_splits {
+        run_file.commit(timer.read().run());
+        /* A failed save leaves the run marked as modified, and is shown */
+        let _ = run_file.save(timer.read().run());
+    } else if let Some(run) = run_file.practice_run().cloned() {
+        let _ = timer.write().set_run(run);
+    }
 }
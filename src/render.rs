@@ -0,0 +1,982 @@
+use big_digits;
+use chrono::{DateTime, Local, Utc};
+use delta_graph::{self, Ink};
+use editor::{self, Editor};
+use history::{self, AttemptHistory, Page, SegmentHistory, SortBy};
+use keymap::Action;
+use layout::{Layout, ComponentState};
+use livesplit_core::TimingMethod;
+use livesplit_core::layout::GeneralSettings;
+use livesplit_core::settings::SemanticColor;
+use livesplit_core::run::editor::SelectionState;
+use livesplit_core::component::{title, splits, timer, detailed_timer, graph, text};
+use run_file::RunFile;
+use stats::{AttemptStats, SegmentStats};
+use std::cmp::{min, max};
+use time_format::format_time;
+use tui::Terminal;
+use tui::backend::TermionBackend;
+use tui::buffer::Buffer;
+use tui::layout::{Group, Direction, Size, Rect};
+use tui::widgets::{Table, Widget, Paragraph, Block, border};
+use tui::style::{Color, Style, Modifier};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/* Columns used by the delta and time columns of the splits table */
+const SPLIT_TIME_WIDTH: u16 = 9;
+
+/* Rows above the first split in the splits table */
+const SPLITS_HEADER_HEIGHT: u16 = 2;
+
+/* Buttons along the bottom when the mouse is enabled */
+const BUTTONS: [(&'static str, Action); 4] = [("Split", Action::SplitOrStart),
+                                              ("Undo", Action::UndoSplit),
+                                              ("Skip", Action::SkipSplit),
+                                              ("Reset", Action::Reset)];
+
+/* Parts of the screen that react to mouse clicks */
+pub enum Target {
+    /* Row of the splits list, out of how many rows are shown */
+    Split(usize, usize),
+    Button(Action),
+}
+
+/* Modal dialogs drawn on top of the layout */
+pub enum Overlay {
+    ConfirmReset,
+    /* Loading times typed so far */
+    LoadingTimes(String),
+    /* Index of the comparison under the cursor */
+    Comparisons(usize),
+}
+
+/* Convert Livesplit display color to a tui color */
+fn get_tui_color(color: ::livesplit_core::settings::Color) -> Color {
+    return Color::Rgb((color.rgba.red * 255.0) as u8,
+                      (color.rgba.green * 255.0) as u8,
+                      (color.rgba.blue * 255.0) as u8);
+}
+
+/* Update display, returning where things can be clicked. `hint` is shown below
+ * the layout, and `buttons` adds a row of buttons for the mouse. */
+pub fn draw(t: &mut Terminal<TermionBackend>, layout: &mut Layout, layout_settings: &GeneralSettings,
+            run_file: &RunFile, overlay: &Option<Overlay>, hint: Option<&str>, buttons: bool)
+            -> Vec<(Rect, Target)> {
+    let size = t.size().unwrap();
+
+    let mut states = layout.states(layout_settings);
+
+    /* Only game time gets a label, real time is the norm */
+    let game_time_label = {
+        let timer = layout.timer.read();
+        match timer.current_timing_method() {
+            TimingMethod::GameTime if timer.is_game_time_paused() => "Game Time (paused)",
+            TimingMethod::GameTime => "Game Time",
+            TimingMethod::RealTime => "",
+        }
+    };
+
+    /* Fall back to a plain text timer if a big one would leave no room for a split */
+    let splits_count = states.iter().filter(|s| is_splits(s)).count() as u16;
+    let button_rows = if buttons { 1 } else { 0 };
+    let others = |compact| -> u16 {
+        button_rows + states.iter().filter(|s| !is_splits(s)).map(|s| height(s, compact)).sum::<u16>()
+    };
+    let compact = others(false) + 2 + 4 * splits_count > size.height;
+    let others = others(compact);
+
+    /* Shrink the splits window to whatever the other components leave over */
+    if splits_count > 0 {
+        let rows = size.height.saturating_sub(2 + others + 3 * splits_count) / splits_count;
+        if layout.fit_splits(rows as usize) {
+            states = layout.states(layout_settings);
+        }
+    }
+
+    let mut sizes = states.iter()
+        .map(|state| Size::Fixed(height(state, compact)))
+        .collect::<Vec<_>>();
+    if buttons {
+        sizes.push(Size::Fixed(button_rows));
+    }
+
+    let mut targets = Vec::new();
+    Group::default()
+        .margin(1)
+        .sizes(&sizes)
+        .direction(Direction::Vertical)
+        .render(t, &size, |t, chunks| {
+            for (state, chunk) in states.iter().zip(chunks.iter()) {
+                let semantic = |color: SemanticColor| get_tui_color(color.visualize(layout_settings));
+                match *state {
+                    ComponentState::Title(ref state) => {
+                        Title { state: state, modified: run_file.is_modified() }.render(t, chunk)
+                    }
+                    ComponentState::Splits(ref state) => {
+                        Splits { state: state, layout_settings: layout_settings }.render(t, chunk);
+                        let rows = state.splits.len();
+                        for row in 0..rows {
+                            let y = chunk.y + SPLITS_HEADER_HEIGHT + row as u16;
+                            if y < chunk.y + chunk.height {
+                                targets.push((Rect::new(chunk.x, y, chunk.width, 1), Target::Split(row, rows)));
+                            }
+                        }
+                    }
+                    ComponentState::Timer(ref state, _) => {
+                        Timer { state: state, label: game_time_label, layout_settings: layout_settings }
+                            .render(t, chunk)
+                    }
+                    ComponentState::PreviousSegment(ref state) => {
+                        InfoText { text: &state.text, value: &state.time, color: semantic(state.semantic_color) }
+                            .render(t, chunk)
+                    }
+                    ComponentState::SumOfBest(ref state) => {
+                        InfoText { text: &state.text, value: &state.time, color: Color::White }.render(t, chunk)
+                    }
+                    ComponentState::PossibleTimeSave(ref state) => {
+                        InfoText { text: &state.text, value: &state.time, color: Color::White }.render(t, chunk)
+                    }
+                    ComponentState::CurrentComparison(ref state) => {
+                        let text = format!("{}:", state.text);
+                        InfoText { text: &text, value: &state.comparison, color: Color::White }.render(t, chunk)
+                    }
+                    ComponentState::CurrentPace(ref state) => {
+                        InfoText { text: &state.text, value: &state.time, color: Color::White }.render(t, chunk)
+                    }
+                    ComponentState::Delta(ref state) => {
+                        InfoText { text: &state.text, value: &state.time, color: semantic(state.semantic_color) }
+                            .render(t, chunk)
+                    }
+                    ComponentState::TotalPlaytime(ref state) => {
+                        InfoText { text: &state.text, value: &state.time, color: Color::White }.render(t, chunk)
+                    }
+                    ComponentState::DetailedTimer(ref state, rows) => {
+                        DetailedTimer {
+                            state: state,
+                            rows: if compact { 1 } else { rows },
+                            label: game_time_label,
+                            layout_settings: layout_settings,
+                        }.render(t, chunk)
+                    }
+                    ComponentState::Text(ref state) => Text { state: state }.render(t, chunk),
+                    ComponentState::Separator(_) => Separator.render(t, chunk),
+                    ComponentState::BlankSpace(..) => {}
+                    ComponentState::Graph(ref state, _) => {
+                        Graph { state: state, layout_settings: layout_settings }.render(t, chunk)
+                    }
+                }
+            }
+            if buttons {
+                let area = &chunks[states.len()];
+                Buttons.render(t, area);
+                targets.extend(Buttons::targets(area));
+            }
+        });
+
+    /* Practicing is flagged in the top margin, so it can't be missed */
+    if run_file.is_practicing() {
+        Paragraph::default()
+            .text(&center(" PRACTICE ", size.width as usize))
+            .style(Style::default().fg(Color::Black).bg(Color::Yellow).modifier(Modifier::Bold))
+            .render(t, &Rect::new(size.x, size.y, size.width, 1));
+    }
+
+    /* So are hints and failed saves, in the bottom margin */
+    let status = match hint {
+        Some(hint) => Some((hint, Color::Yellow)),
+        None => run_file.error().map(|error| (error, Color::Red)),
+    };
+    if let Some((text, color)) = status {
+        Paragraph::default()
+            .text(&truncate(text, size.width as usize))
+            .style(Style::default().fg(color))
+            .render(t, &Rect::new(size.x, size.y + size.height.saturating_sub(1), size.width, 1));
+    }
+
+    match *overlay {
+        Some(Overlay::ConfirmReset) if run_file.is_practicing() => {
+            draw_dialog(t, &size, "Reset", &["Keep this practice attempt in the splits? [y/n/cancel]"]);
+        }
+        Some(Overlay::ConfirmReset) => {
+            draw_dialog(t, &size, "Reset", &["Update splits? [y/n/cancel]"]);
+        }
+        Some(Overlay::LoadingTimes(ref text)) => {
+            let input = format!("{}_", text);
+            draw_dialog(t, &size, "Loading times", &[input.as_str(), "[enter] set  [esc] cancel"]);
+        }
+        Some(Overlay::Comparisons(selected)) => {
+            /* Number the first nine for picking directly, and star the active one */
+            let timer = layout.timer.read();
+            let lines = timer.run()
+                .comparisons()
+                .enumerate()
+                .map(|(i, name)| {
+                    let number = if i < 9 { (i + 1).to_string() } else { String::from(" ") };
+                    let active = if name == timer.current_comparison() { "*" } else { " " };
+                    format!("{} {} {}", number, active, name)
+                })
+                .collect::<Vec<_>>();
+            let mut lines = lines.iter().map(|l| l.as_str()).collect::<Vec<_>>();
+            lines.push("[enter] compare  [esc] cancel");
+
+            let area = draw_dialog(t, &size, "Compare against", &lines);
+            if 1 + selected as u16 + 1 < area.height {
+                Paragraph::default()
+                    .text(&format!(" {:<width$} ", lines[selected], width = area.width.saturating_sub(4) as usize))
+                    .style(Style::default().fg(Color::Black).bg(Color::White))
+                    .render(t, &Rect::new(area.x + 1, area.y + 1 + selected as u16, area.width.saturating_sub(2), 1));
+            }
+        }
+        None => {}
+    }
+
+    t.draw().unwrap();
+    targets
+}
+
+/* What was drawn at a (zero based) position */
+pub fn target_at(targets: &[(Rect, Target)], x: u16, y: u16) -> Option<&Target> {
+    targets.iter()
+        .find(|&&(ref area, _)| {
+            x >= area.x && x < area.x + area.width && y >= area.y && y < area.y + area.height
+        })
+        .map(|&(_, ref target)| target)
+}
+
+/* Rows each component takes up. Compact layouts draw the timer as plain text. */
+fn height(state: &ComponentState, compact: bool) -> u16 {
+    match *state {
+        ComponentState::Title(_) => 3,
+        ComponentState::Splits(ref state) => state.splits.len() as u16 + 3,
+        ComponentState::Timer(_, rows) => if compact { 2 } else { max(rows, 2) },
+        ComponentState::DetailedTimer(ref state, rows) => {
+            let rows = if compact { 1 } else { max(rows, 1) };
+            rows + 1 + state.comparison1.iter().count() as u16 + state.comparison2.iter().count() as u16
+        }
+        ComponentState::BlankSpace(_, rows) => rows,
+        ComponentState::Graph(_, rows) => rows,
+        ComponentState::PreviousSegment(_) |
+        ComponentState::SumOfBest(_) |
+        ComponentState::PossibleTimeSave(_) |
+        ComponentState::CurrentComparison(_) |
+        ComponentState::CurrentPace(_) |
+        ComponentState::Delta(_) |
+        ComponentState::TotalPlaytime(_) |
+        ComponentState::Text(_) |
+        ComponentState::Separator(_) => 1,
+    }
+}
+
+fn is_splits(state: &ComponentState) -> bool {
+    match *state {
+        ComponentState::Splits(_) => true,
+        _ => false,
+    }
+}
+
+/* The components as tui widgets, each drawing one frame's state of itself */
+
+struct Title<'a> {
+    state: &'a title::State,
+    /* Flag unsaved changes next to the game name */
+    modified: bool,
+}
+
+impl<'a> Widget for Title<'a> {
+    fn draw(&self, area: &Rect, buf: &mut Buffer) {
+        let line1 = if self.modified {
+            format!("{} *", self.state.line1)
+        } else {
+            self.state.line1.clone()
+        };
+
+        let width = area.width as usize;
+        let line2 = self.state.line2.clone().unwrap_or_default();
+        let attempts = self.state.attempts.map(|a| a.to_string()).unwrap_or_default();
+
+        /* Keep the category centered, with the attempt count on the right */
+        let room = width.saturating_sub(2 * (attempts.width() + 1));
+        let category = truncate(&line2, room);
+        let left = (width - category.width()) / 2;
+        let right = width.saturating_sub(left + category.width() + attempts.width());
+
+        Paragraph::default()
+            .text(&format!("{}\n{}{}{}{}",
+                           center(&line1, width),
+                           spaces(left),
+                           category,
+                           spaces(right),
+                           attempts))
+            .draw(area, buf);
+    }
+}
+
+struct Splits<'a> {
+    state: &'a splits::State,
+    layout_settings: &'a GeneralSettings,
+}
+
+impl<'a> Widget for Splits<'a> {
+    fn draw(&self, area: &Rect, buf: &mut Buffer) {
+        let styles = self.state
+            .splits
+            .iter()
+            .map(|s| Style::default().fg(get_tui_color(s.semantic_color.visualize(self.layout_settings))))
+            .collect::<Vec<_>>();
+
+        /* The name column gets whatever the times don't need */
+        let name_width = area.width.saturating_sub(2 * (SPLIT_TIME_WIDTH + 1));
+        let time_width = SPLIT_TIME_WIDTH as usize;
+
+        let splits = self.state
+            .splits
+            .iter()
+            .zip(styles.iter())
+            .map(|(s, style)| {
+                ([truncate(&s.name, name_width as usize),
+                  align_right(&s.delta, time_width),
+                  align_right(&s.time, time_width)],
+                 style)
+            })
+            .collect::<Vec<_>>();
+
+        Table::default()
+            .header(&[String::from("Split"), align_right("Delta", time_width), align_right("Time", time_width)])
+            .header_style(Style::default().fg(Color::White))
+            .widths(&[name_width, SPLIT_TIME_WIDTH, SPLIT_TIME_WIDTH])
+            .style(Style::default().fg(Color::White))
+            .column_spacing(1)
+            .rows(&splits)
+            .draw(area, buf);
+    }
+}
+
+/* The time in block digits if it fits next to the label, plain text otherwise */
+struct Timer<'a> {
+    state: &'a timer::State,
+    label: &'a str,
+    layout_settings: &'a GeneralSettings,
+}
+
+impl<'a> Widget for Timer<'a> {
+    fn draw(&self, area: &Rect, buf: &mut Buffer) {
+        let time = format!("{}{}", self.state.time, self.state.fraction);
+        let color = get_tui_color(self.state.semantic_color.visualize(self.layout_settings));
+        let width = area.width as usize;
+
+        let big = big_digits::font_height(area.height)
+            .and_then(|height| big_digits::render(&time, height))
+            .and_then(|lines| if lines[0].width() + self.label.width() < width { Some(lines) } else { None });
+
+        let text = match big {
+            Some(lines) => {
+                lines.iter()
+                    .enumerate()
+                    .map(|(i, line)| {
+                        let label = if i == 0 { self.label } else { "" };
+                        format_info_text(label, line, area.width)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            None => format_info_text(self.label, &time, area.width),
+        };
+
+        Paragraph::default()
+            .text(&text)
+            .style(Style::default().modifier(Modifier::Bold).fg(color))
+            .draw(area, buf);
+    }
+}
+
+/* The run's timer in its first `rows` rows, then the segment timer and the
+ * times of up to two comparisons for the current segment */
+struct DetailedTimer<'a> {
+    state: &'a detailed_timer::State,
+    rows: u16,
+    label: &'a str,
+    layout_settings: &'a GeneralSettings,
+}
+
+impl<'a> Widget for DetailedTimer<'a> {
+    fn draw(&self, area: &Rect, buf: &mut Buffer) {
+        let rows = min(max(self.rows, 1), area.height);
+        let timer = Timer {
+            state: &self.state.timer,
+            label: self.label,
+            layout_settings: self.layout_settings,
+        };
+        timer.draw(&Rect::new(area.x, area.y, area.width, rows), buf);
+
+        let segment_time = format!("{}{}", self.state.segment_timer.time, self.state.segment_timer.fraction);
+        let mut lines = vec![format_info_text("Segment", &segment_time, area.width)];
+        for comparison in self.state.comparison1.iter().chain(self.state.comparison2.iter()) {
+            lines.push(format_info_text(&comparison.name, &comparison.time, area.width));
+        }
+
+        Paragraph::default()
+            .text(&lines.join("\n"))
+            .style(Style::default().fg(Color::White))
+            .draw(&Rect::new(area.x, area.y + rows, area.width, area.height - rows), buf);
+    }
+}
+
+/* A single line with a label on the left and a value on the right, which is
+ * what most components come down to */
+struct InfoText<'a> {
+    text: &'a str,
+    value: &'a str,
+    color: Color,
+}
+
+impl<'a> Widget for InfoText<'a> {
+    fn draw(&self, area: &Rect, buf: &mut Buffer) {
+        Paragraph::default()
+            .text(&format_info_text(self.text, self.value, area.width))
+            .style(Style::default().fg(self.color))
+            .draw(area, buf);
+    }
+}
+
+struct Text<'a> {
+    state: &'a text::State,
+}
+
+impl<'a> Widget for Text<'a> {
+    fn draw(&self, area: &Rect, buf: &mut Buffer) {
+        let line = match self.state.text {
+            text::Text::Center(ref text) => center(text, area.width as usize),
+            text::Text::Split(ref left, ref right) => format_info_text(left, right, area.width),
+        };
+        Paragraph::default()
+            .text(&line)
+            .style(Style::default().fg(Color::White))
+            .draw(area, buf);
+    }
+}
+
+struct Separator;
+
+impl Widget for Separator {
+    fn draw(&self, area: &Rect, buf: &mut Buffer) {
+        Paragraph::default()
+            .text(&"─".repeat(area.width as usize))
+            .style(Style::default().fg(Color::Gray))
+            .draw(area, buf);
+    }
+}
+
+/* The delta graph in braille, one paragraph per run of cells of the same color */
+struct Graph<'a> {
+    state: &'a graph::State,
+    layout_settings: &'a GeneralSettings,
+}
+
+impl<'a> Widget for Graph<'a> {
+    fn draw(&self, area: &Rect, buf: &mut Buffer) {
+        let color = |ink| match ink {
+            Ink::Empty | Ink::Comparison => Color::DarkGray,
+            Ink::Line => Color::White,
+            Ink::Ahead => get_tui_color(SemanticColor::AheadGainingTime.visualize(self.layout_settings)),
+            Ink::Behind => get_tui_color(SemanticColor::BehindLosingTime.visualize(self.layout_settings)),
+            Ink::Gold => get_tui_color(SemanticColor::BestSegment.visualize(self.layout_settings)),
+        };
+
+        for (y, row) in delta_graph::render(self.state, area.width, area.height).iter().enumerate() {
+            let mut start = 0;
+            while start < row.len() {
+                let ink = row[start].1;
+                let end = row[start..].iter().position(|&(_, i)| i != ink).map_or(row.len(), |n| start + n);
+                let text = row[start..end].iter().map(|&(symbol, _)| symbol).collect::<String>();
+                Paragraph::default()
+                    .text(&text)
+                    .style(Style::default().fg(color(ink)))
+                    .draw(&Rect::new(area.x + start as u16, area.y + y as u16, (end - start) as u16, 1), buf);
+                start = end;
+            }
+        }
+    }
+}
+
+/* The row of mouse buttons along the bottom */
+struct Buttons;
+
+impl Buttons {
+    /* Where each button that fits goes */
+    fn targets(area: &Rect) -> Vec<(Rect, Target)> {
+        let mut targets = Vec::new();
+        let mut x = area.x;
+        for &(label, action) in BUTTONS.iter() {
+            let width = button_label(label).width() as u16;
+            if x + width > area.x + area.width {
+                break;
+            }
+            targets.push((Rect::new(x, area.y, width, 1), Target::Button(action)));
+            x += width + 1;
+        }
+        targets
+    }
+}
+
+impl Widget for Buttons {
+    fn draw(&self, area: &Rect, buf: &mut Buffer) {
+        let text = BUTTONS.iter()
+            .take(Buttons::targets(area).len())
+            .map(|&(label, _)| button_label(label))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Paragraph::default()
+            .text(&text)
+            .style(Style::default().fg(Color::White))
+            .draw(area, buf);
+    }
+}
+
+fn button_label(label: &str) -> String {
+    format!("[ {} ]", label)
+}
+
+/* Draw the run editor in place of the layout */
+pub fn draw_editor(t: &mut Terminal<TermionBackend>, editor: &mut Editor) {
+    let size = t.size().unwrap();
+    let state = editor.state();
+    let input = editor.input().map(|i| format!("{}: {}_", i.field.label(), i.text));
+    let error = editor.error().map(String::from);
+
+    Group::default()
+        .margin(1)
+        .sizes(&[Size::Fixed(4), Size::Min(3), Size::Fixed(3)])
+        .direction(Direction::Vertical)
+        .render(t, &size, |t, chunks| {
+            let width = chunks[0].width;
+            Paragraph::default()
+                .text(&[format_info_text("Game", &state.game, width),
+                        format_info_text("Category", &state.category, width),
+                        format_info_text("Offset", &state.offset, width),
+                        format_info_text("Attempts", &state.attempts.to_string(), width)]
+                    .join("\n"))
+                .style(Style::default().fg(Color::White))
+                .render(t, &chunks[0]);
+
+            /* Keep the active segment in view */
+            let area = &chunks[1];
+            let visible = area.height.saturating_sub(2) as usize;
+            let active = state.segments
+                .iter()
+                .position(|s| s.selected == SelectionState::Active)
+                .unwrap_or(0);
+            let first = (active + 1).saturating_sub(visible);
+
+            let name_width = area.width.saturating_sub(3 * (SPLIT_TIME_WIDTH + 1));
+            let time_width = SPLIT_TIME_WIDTH as usize;
+            let normal = Style::default().fg(Color::White);
+            let selected = Style::default().fg(Color::Yellow);
+            let rows = state.segments
+                .iter()
+                .skip(first)
+                .take(visible)
+                .map(|s| {
+                    let style = if s.selected == SelectionState::NotSelected { &normal } else { &selected };
+                    ([truncate(&s.name, name_width as usize),
+                      align_right(&s.split_time, time_width),
+                      align_right(&s.segment_time, time_width),
+                      align_right(&s.best_segment_time, time_width)],
+                     style)
+                })
+                .collect::<Vec<_>>();
+
+            Table::default()
+                .header(&[String::from("Segment"),
+                          align_right("Split", time_width),
+                          align_right("Segment", time_width),
+                          align_right("Best", time_width)])
+                .header_style(Style::default().fg(Color::White))
+                .widths(&[name_width, SPLIT_TIME_WIDTH, SPLIT_TIME_WIDTH, SPLIT_TIME_WIDTH])
+                .style(normal)
+                .column_spacing(1)
+                .rows(&rows)
+                .render(t, area);
+
+            /* Errors replace the help, and turn the status red */
+            let color = if error.is_some() { Color::Red } else { Color::White };
+            let help = error.clone().unwrap_or(String::from(editor::HELP));
+            Paragraph::default()
+                .text(&format!("{}\n{}", truncate(&input.clone().unwrap_or_default(), width as usize), help))
+                .wrap(true)
+                .style(Style::default().fg(color))
+                .render(t, &chunks[2]);
+        });
+
+    t.draw().unwrap();
+}
+
+/* Draw the segment history in place of the layout: every segment's stats,
+ * then the times of the selected one, newest first */
+pub fn draw_segment_history(t: &mut Terminal<TermionBackend>, view: &SegmentHistory, stats: &[SegmentStats]) {
+    let size = t.size().unwrap();
+    let order = view.order(stats);
+
+    /* The segment list gets up to half of the screen */
+    let inner_height = size.height.saturating_sub(2 + 2);
+    let list_height = min(order.len() as u16 + 2, max(inner_height / 2, 3));
+
+    Group::default()
+        .margin(1)
+        .sizes(&[Size::Fixed(list_height), Size::Min(3), Size::Fixed(2)])
+        .direction(Direction::Vertical)
+        .render(t, &size, |t, chunks| {
+            let time_width = SPLIT_TIME_WIDTH as usize;
+            let count_width = 5;
+            let normal = Style::default().fg(Color::White);
+            let selected = Style::default().fg(Color::Yellow);
+
+            /* Keep the selected segment in view */
+            let area = &chunks[0];
+            let visible = area.height.saturating_sub(2) as usize;
+            let row = order.iter().position(|&i| i == view.segment).unwrap_or(0);
+            let first = (row + 1).saturating_sub(visible);
+
+            let name_width = area.width.saturating_sub(4 * (SPLIT_TIME_WIDTH + 1) + count_width + 1);
+            let rows = order.iter()
+                .skip(first)
+                .take(visible)
+                .map(|&i| {
+                    let s = &stats[i];
+                    let style = if i == view.segment { &selected } else { &normal };
+                    ([truncate(&s.name, name_width as usize),
+                      align_right(&format_time(s.best), time_width),
+                      align_right(&format_time(s.average), time_width),
+                      align_right(&format_time(s.median), time_width),
+                      align_right(&format_time(s.std_dev), time_width),
+                      align_right(&s.count().to_string(), count_width as usize)],
+                     style)
+                })
+                .collect::<Vec<_>>();
+
+            /* Mark the column being sorted by */
+            let arrow = if view.descending { "▼" } else { "▲" };
+            let header = [SortBy::Order, SortBy::Best, SortBy::Average, SortBy::Median, SortBy::StdDev, SortBy::Count]
+                .iter()
+                .map(|&sort| {
+                    if sort == view.sort {
+                        format!("{}{}", sort.label(), arrow)
+                    } else {
+                        sort.label().to_string()
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            Table::default()
+                .header(&[header[0].clone(),
+                          align_right(&header[1], time_width),
+                          align_right(&header[2], time_width),
+                          align_right(&header[3], time_width),
+                          align_right(&header[4], time_width),
+                          align_right(&header[5], count_width as usize)])
+                .header_style(normal)
+                .widths(&[name_width, SPLIT_TIME_WIDTH, SPLIT_TIME_WIDTH, SPLIT_TIME_WIDTH, SPLIT_TIME_WIDTH, count_width])
+                .style(normal)
+                .column_spacing(1)
+                .rows(&rows)
+                .render(t, area);
+
+            /* Times of the selected segment, with how far off the best they were */
+            let area = &chunks[1];
+            let visible = area.height.saturating_sub(2) as usize;
+            let empty = Vec::new();
+            let (times, best) = match stats.get(view.segment) {
+                Some(s) => (&s.times, s.best),
+                None => (&empty, None),
+            };
+            let rows = times.iter()
+                .rev()
+                .skip(view.scroll)
+                .take(visible)
+                .map(|&(id, time)| {
+                    let gold = best.map(|best| time.total_seconds() <= best.total_seconds()).unwrap_or(false);
+                    let style = if gold { &selected } else { &normal };
+                    ([id.to_string(),
+                      align_right(&format_time(Some(time)), time_width),
+                      align_right(&format_time(best.map(|best| time - best)), time_width)],
+                     style)
+                })
+                .collect::<Vec<_>>();
+
+            Table::default()
+                .header(&[String::from("Attempt"), align_right("Time", time_width), align_right("+Best", time_width)])
+                .header_style(normal)
+                .widths(&[SPLIT_TIME_WIDTH, SPLIT_TIME_WIDTH, SPLIT_TIME_WIDTH])
+                .style(normal)
+                .column_spacing(1)
+                .rows(&rows)
+                .render(t, area);
+
+            Paragraph::default()
+                .text(history::SEGMENT_HELP)
+                .wrap(true)
+                .style(normal)
+                .render(t, &chunks[2]);
+        });
+
+    t.draw().unwrap();
+}
+
+/* Draw the attempt history in place of the layout: totals on top, then a page
+ * of attempts, resets per segment or personal bests */
+pub fn draw_attempt_history(t: &mut Terminal<TermionBackend>, view: &AttemptHistory, stats: &AttemptStats) {
+    let size = t.size().unwrap();
+    let time_width = SPLIT_TIME_WIDTH as usize;
+    let normal = Style::default().fg(Color::White);
+    let percent = |rate: Option<f64>| rate.map(|r| format!("{:.1}%", r * 100.0)).unwrap_or(String::from("-"));
+    let date = |time: Option<DateTime<Utc>>| {
+        time.map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or(String::from("-"))
+    };
+
+    Group::default()
+        .margin(1)
+        .sizes(&[Size::Fixed(4), Size::Min(3), Size::Fixed(1)])
+        .direction(Direction::Vertical)
+        .render(t, &size, |t, chunks| {
+            let width = chunks[0].width;
+            let personal_best = stats.pb_progression.last().and_then(|&i| stats.attempts[i].time);
+            let pages = history::PAGES
+                .iter()
+                .map(|&page| {
+                    if page == view.page {
+                        format!("[{}]", page.label())
+                    } else {
+                        format!(" {} ", page.label())
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            Paragraph::default()
+                .text(&[format_info_text("Attempts",
+                                         &format!("{} ({} finished, {})",
+                                                  stats.attempts.len(),
+                                                  stats.finished,
+                                                  percent(stats.completion_rate())),
+                                         width),
+                        format_info_text("Total playtime", &format_time(Some(stats.playtime)), width),
+                        format_info_text("Personal best", &format_time(personal_best), width),
+                        pages]
+                    .join("\n"))
+                .style(normal)
+                .render(t, &chunks[0]);
+
+            let area = &chunks[1];
+            let visible = area.height.saturating_sub(2) as usize;
+            match view.page {
+                Page::Attempts => {
+                    let date_width = 16;
+                    let reset_width = area.width.saturating_sub(5 + 2 * date_width + 2 * SPLIT_TIME_WIDTH + 5);
+                    let rows = stats.attempts
+                        .iter()
+                        .rev()
+                        .skip(view.scroll)
+                        .take(visible)
+                        .map(|a| {
+                            let reset = a.reset_segment
+                                .and_then(|i| stats.resets.get(i))
+                                .map(|s| s.name.clone())
+                                .unwrap_or_default();
+                            ([a.index.to_string(),
+                              date(a.started),
+                              date(a.ended),
+                              align_right(&format_time(a.time), time_width),
+                              align_right(&format_time(a.pause_time), time_width),
+                              truncate(&reset, reset_width as usize)],
+                             &normal)
+                        })
+                        .collect::<Vec<_>>();
+
+                    Table::default()
+                        .header(&[String::from("#"),
+                                  String::from("Started"),
+                                  String::from("Ended"),
+                                  align_right("Time", time_width),
+                                  align_right("Pause", time_width),
+                                  String::from("Reset in")])
+                        .header_style(normal)
+                        .widths(&[5, date_width, date_width, SPLIT_TIME_WIDTH, SPLIT_TIME_WIDTH, reset_width])
+                        .style(normal)
+                        .column_spacing(1)
+                        .rows(&rows)
+                        .render(t, area);
+                }
+                Page::Resets => {
+                    let count_width = 7;
+                    let name_width = area.width.saturating_sub(3 * (count_width + 1));
+                    let rows = stats.resets
+                        .iter()
+                        .skip(view.scroll)
+                        .take(visible)
+                        .map(|s| {
+                            ([truncate(&s.name, name_width as usize),
+                              align_right(&s.reached.to_string(), count_width as usize),
+                              align_right(&s.resets.to_string(), count_width as usize),
+                              align_right(&percent(s.rate()), count_width as usize)],
+                             &normal)
+                        })
+                        .collect::<Vec<_>>();
+
+                    Table::default()
+                        .header(&[String::from("Segment"),
+                                  align_right("Reached", count_width as usize),
+                                  align_right("Resets", count_width as usize),
+                                  align_right("Rate", count_width as usize)])
+                        .header_style(normal)
+                        .widths(&[name_width, count_width, count_width, count_width])
+                        .style(normal)
+                        .column_spacing(1)
+                        .rows(&rows)
+                        .render(t, area);
+                }
+                Page::Progression => {
+                    /* Each personal best, with how much it took off the one before */
+                    let rows = stats.pb_progression
+                        .iter()
+                        .enumerate()
+                        .skip(view.scroll)
+                        .take(visible)
+                        .map(|(n, &i)| {
+                            let attempt = &stats.attempts[i];
+                            let previous = if n > 0 { stats.attempts[stats.pb_progression[n - 1]].time } else { None };
+                            let improvement = match (attempt.time, previous) {
+                                (Some(time), Some(previous)) => Some(time - previous),
+                                _ => None,
+                            };
+                            ([attempt.index.to_string(),
+                              date(attempt.ended.or(attempt.started)),
+                              align_right(&format_time(attempt.time), time_width),
+                              align_right(&format_time(improvement), time_width)],
+                             &normal)
+                        })
+                        .collect::<Vec<_>>();
+
+                    Table::default()
+                        .header(&[String::from("#"),
+                                  String::from("Date"),
+                                  align_right("Time", time_width),
+                                  align_right("Change", time_width)])
+                        .header_style(normal)
+                        .widths(&[5, 16, SPLIT_TIME_WIDTH, SPLIT_TIME_WIDTH])
+                        .style(normal)
+                        .column_spacing(1)
+                        .rows(&rows)
+                        .render(t, area);
+                }
+            }
+
+            Paragraph::default()
+                .text(history::ATTEMPT_HELP)
+                .style(normal)
+                .render(t, &chunks[2]);
+        });
+
+    t.draw().unwrap();
+}
+
+/* Draw a boxed message centered over the layout, returning the area it
+ * covers, border included */
+fn draw_dialog(t: &mut Terminal<TermionBackend>, size: &Rect, title: &str, lines: &[&str]) -> Rect {
+    let text_width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let width = min(text_width as u16 + 4, size.width);
+    let height = min(lines.len() as u16 + 2, size.height);
+    let area = Rect::new(size.x + (size.width - width) / 2,
+                         size.y + (size.height - height) / 2,
+                         width,
+                         height);
+
+    /* Pad every line so the dialog covers whatever is underneath it */
+    let text = lines.iter()
+        .map(|l| format!(" {:<width$} ", l, width = text_width))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Paragraph::default()
+        .block(Block::default().borders(border::ALL).title(title))
+        .text(&text)
+        .style(Style::default().fg(Color::White))
+        .render(t, &area);
+    area
+}
+
+/* Label on the left, value on the right, cutting the label short if needed */
+fn format_info_text(text: &str, value: &str, width: u16) -> String {
+    let width = width as usize;
+    let value = truncate(value, width);
+    let text = truncate(text, width.saturating_sub(value.width() + 1));
+    let gap = width.saturating_sub(text.width() + value.width());
+    format!("{}{}{}", text, spaces(gap), value)
+}
+
+/* Cut text down to `width` columns, ending in an ellipsis if anything was cut */
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+
+    let mut result = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        result.push(c);
+        used += char_width;
+    }
+    result.push('…');
+    result
+}
+
+fn align_right(text: &str, width: usize) -> String {
+    let text = truncate(text, width);
+    format!("{}{}", spaces(width - text.width()), text)
+}
+
+fn center(text: &str, width: usize) -> String {
+    let text = truncate(text, width);
+    let left = (width - text.width()) / 2;
+    format!("{}{}{}", spaces(left), text, spaces(width - left - text.width()))
+}
+
+fn spaces(count: usize) -> String {
+    " ".repeat(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncates_to_the_width_in_columns() {
+        assert_eq!(truncate("Hello", 10), "Hello");
+        assert_eq!(truncate("Hello", 5), "Hello");
+        assert_eq!(truncate("Hello", 4), "Hel…");
+        assert_eq!(truncate("Hello", 1), "…");
+        assert_eq!(truncate("Hello", 0), "");
+        /* Wide characters take two columns */
+        assert_eq!(truncate("日本語", 4), "日…");
+    }
+
+    #[test]
+    fn fits_info_text_to_the_width() {
+        assert_eq!(format_info_text("Sum of Best", "1:23.45", 20), "Sum of Best  1:23.45");
+        assert_eq!(format_info_text("Sum of Best", "1:23.45", 12), "Sum… 1:23.45");
+        assert_eq!(format_info_text("Sum of Best", "1:23.45", 5), "1:23…");
+    }
+
+    #[test]
+    fn centers_text() {
+        assert_eq!(center("ab", 6), "  ab  ");
+        assert_eq!(center("abc", 6), " abc  ");
+        assert_eq!(center("too long", 4), "too…");
+    }
+}
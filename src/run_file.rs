@@ -0,0 +1,324 @@
+use chrono::Local;
+use livesplit_core::Run;
+use livesplit_core::run::parser::composite;
+use livesplit_core::run::saver::livesplit;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/* The splits file a Run was loaded from, and whether it has unsaved changes */
+pub struct RunFile {
+    path: Option<String>,
+    backups: usize,
+    modified: bool,
+    /* Why the last save failed, until one succeeds */
+    error: Option<String>,
+    /* While practicing, the run without the practice attempts. It's what
+     * gets saved, and what the timer goes back to after each attempt. */
+    practice: Option<Run>,
+}
+
+impl RunFile {
+    /* Keep up to `backups` previous versions of the file around when saving */
+    pub fn new(path: Option<String>, backups: usize) -> RunFile {
+        RunFile {
+            path: path,
+            backups: backups,
+            modified: false,
+            error: None,
+            practice: None,
+        }
+    }
+
+    pub fn has_file(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /* Runs without a file on disk are never reported as modified */
+    pub fn is_modified(&self) -> bool {
+        self.path.is_some() && self.modified
+    }
+
+    /* Practice attempts don't count as changes */
+    pub fn mark_modified(&mut self) {
+        if self.practice.is_none() {
+            self.modified = true;
+        }
+    }
+
+    /* Keep `run` as it is, even while practicing */
+    pub fn commit(&mut self, run: &Run) {
+        if let Some(ref mut practice) = self.practice {
+            *practice = run.clone();
+        }
+        self.modified = true;
+    }
+
+    pub fn start_practice(&mut self, run: Run) {
+        self.practice = Some(run);
+    }
+
+    pub fn stop_practice(&mut self) {
+        self.practice = None;
+    }
+
+    pub fn is_practicing(&self) -> bool {
+        self.practice.is_some()
+    }
+
+    /* The run to go back to after a practice attempt */
+    pub fn practice_run(&self) -> Option<&Run> {
+        self.practice.as_ref()
+    }
+
+    /* Why the last save failed, if it did */
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_ref().map(|e| e.as_str())
+    }
+
+    /* Write the Run back to its file in LiveSplit .lss format, backing up the
+     * old version first. While practicing, the committed run is written instead.
+     * A failure is also kept around, to be shown until a save succeeds. Without
+     * a file, saving fails so the changes aren't lost unnoticed. */
+    pub fn save(&mut self, run: &Run) -> Result<(), String> {
+        let result = self.write(run);
+        match result {
+            Ok(_) => {
+                self.modified = false;
+                self.error = None;
+            }
+            Err(ref error) => self.error = Some(error.clone()),
+        }
+        result
+    }
+
+    fn write(&self, run: &Run) -> Result<(), String> {
+        let path = match self.path {
+            Some(ref path) => Path::new(path),
+            None => return Err(String::from("Nothing saved, no run file was given")),
+        };
+        let run = self.practice.as_ref().unwrap_or(run);
+
+        back_up(path, self.backups)
+            .map_err(|e| format!("Unable to back up {}: {}", path.display(), e))?;
+        write_atomically(path, |writer| livesplit::save(run, writer))
+            .map_err(|e| format!("Unable to save {}: {}", path.display(), e))
+    }
+}
+
+/* Read a Run from a splits file in any format livesplit-core understands */
+pub fn load(path: &str) -> Result<Run, String> {
+    let file = File::open(path).map_err(|_| format!("Unable to open {}", path))?;
+    composite::parse(BufReader::new(file), Some(PathBuf::from(path)), true)
+        .map_err(|_| format!("Unable to parse {}", path))
+}
+
+/* Backups of a splits file, oldest first */
+pub fn list_backups(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let prefix = backup_prefix(path);
+    let mut backups = Vec::new();
+
+    for entry in fs::read_dir(parent_dir(path))? {
+        let entry = entry?;
+        let is_backup = entry.file_name()
+            .to_str()
+            .map(|name| name.starts_with(&prefix) && name.ends_with(".bak"))
+            .unwrap_or(false);
+        if is_backup {
+            backups.push(entry.path());
+        }
+    }
+
+    /* Timestamps sort chronologically as strings */
+    backups.sort();
+    Ok(backups)
+}
+
+/* Replace a splits file with one of its backups, given its file name or timestamp.
+ * The current version is backed up too, so a restore can itself be undone. */
+pub fn restore_backup(path: &Path, backup: &str, backups: usize) -> Result<PathBuf, String> {
+    let available = list_backups(path)
+        .map_err(|e| format!("Unable to list backups of {}: {}", path.display(), e))?;
+    let wanted = format!("{}{}.bak", backup_prefix(path), backup);
+    let source = available.into_iter()
+        .find(|b| {
+            let name = b.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            name == backup || name == wanted
+        })
+        .ok_or(format!("No backup {} of {}", backup, path.display()))?;
+
+    let mut contents = Vec::new();
+    File::open(&source)
+        .and_then(|mut file| file.read_to_end(&mut contents))
+        .map_err(|e| format!("Unable to read {}: {}", source.display(), e))?;
+
+    back_up(path, backups)
+        .map_err(|e| format!("Unable to back up {}: {}", path.display(), e))?;
+    write_atomically(path, |writer| writer.write_all(&contents))
+        .map_err(|e| format!("Unable to restore {}: {}", path.display(), e))?;
+
+    Ok(source)
+}
+
+/* Copy the current file to a timestamped backup and drop all but the newest `keep` */
+fn back_up(path: &Path, keep: usize) -> io::Result<()> {
+    if keep == 0 || !path.exists() {
+        return Ok(());
+    }
+
+    /* Saving twice within a millisecond still mustn't replace a backup, so a
+     * counter is added then. It keeps the names sorting in order. */
+    let timestamp = Local::now().format("%Y%m%d-%H%M%S%.3f").to_string();
+    let mut backup = parent_dir(path).join(format!("{}{}.bak", backup_prefix(path), timestamp));
+    let mut count = 1;
+    while backup.exists() {
+        backup = parent_dir(path).join(format!("{}{}_{}.bak", backup_prefix(path), timestamp, count));
+        count += 1;
+    }
+    let mut contents = Vec::new();
+    File::open(path)?.read_to_end(&mut contents)?;
+    write_atomically(&backup, |writer| writer.write_all(&contents))?;
+
+    let backups = list_backups(path)?;
+    if backups.len() > keep {
+        for old in &backups[..backups.len() - keep] {
+            fs::remove_file(old)?;
+        }
+    }
+    Ok(())
+}
+
+/* Write to a temporary file next to `path` and rename it into place, so a crash
+ * mid-write leaves either the old or the new file, never a truncated one */
+pub fn write_atomically<F>(path: &Path, write: F) -> io::Result<()>
+    where F: FnOnce(&mut BufWriter<&File>) -> io::Result<()>
+{
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("splits");
+    let temp_path = parent_dir(path).join(format!(".{}.tmp", file_name));
+
+    let result = File::create(&temp_path).and_then(|file| {
+        {
+            let mut writer = BufWriter::new(&file);
+            write(&mut writer)?;
+            writer.flush()?;
+        }
+        file.sync_all()
+    });
+    if let Err(error) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(error);
+    }
+
+    fs::rename(&temp_path, path)?;
+
+    /* Make the rename itself durable */
+    if let Ok(dir) = File::open(parent_dir(path)) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+fn parent_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if parent != Path::new("") => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/* Backups are named <file name>.<timestamp>.bak, the timestamp going down to milliseconds */
+fn backup_prefix(path: &Path) -> String {
+    format!("{}.", path.file_name().and_then(|n| n.to_str()).unwrap_or("splits"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    /* A fresh directory holding a splits file with `contents` */
+    fn splits_file(name: &str, contents: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("livesplit-one-terminal-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("splits.lss");
+        write(&path, contents);
+        path
+    }
+
+    fn write(path: &Path, contents: &str) {
+        File::create(path).and_then(|mut file| file.write_all(contents.as_bytes())).unwrap();
+    }
+
+    fn read(path: &Path) -> String {
+        let mut contents = String::new();
+        File::open(path).and_then(|mut file| file.read_to_string(&mut contents)).unwrap();
+        contents
+    }
+
+    fn name(path: &Path) -> String {
+        path.file_name().unwrap().to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn keeps_the_newest_backups() {
+        let path = splits_file("rotation", "1");
+        for version in 2..6 {
+            back_up(&path, 3).unwrap();
+            write(&path, &version.to_string());
+        }
+
+        let backups = list_backups(&path).unwrap();
+        let contents = backups.iter().map(|b| read(b)).collect::<Vec<_>>();
+        assert_eq!(contents, ["2", "3", "4"]);
+        for backup in &backups {
+            let name = name(backup);
+            assert!(name.starts_with("splits.lss.") && name.ends_with(".bak"), "{}", name);
+        }
+
+        fs::remove_dir_all(parent_dir(&path)).unwrap();
+    }
+
+    #[test]
+    fn backups_made_at_once_get_their_own_names() {
+        let path = splits_file("same-time", "1");
+        for _ in 0..3 {
+            back_up(&path, 10).unwrap();
+        }
+        assert_eq!(list_backups(&path).unwrap().len(), 3);
+
+        fs::remove_dir_all(parent_dir(&path)).unwrap();
+    }
+
+    #[test]
+    fn no_backups_are_made_when_none_are_kept() {
+        let path = splits_file("none", "1");
+        back_up(&path, 0).unwrap();
+        assert!(list_backups(&path).unwrap().is_empty());
+
+        fs::remove_dir_all(parent_dir(&path)).unwrap();
+    }
+
+    #[test]
+    fn restores_by_timestamp_or_name() {
+        let path = splits_file("restore", "old");
+        back_up(&path, 5).unwrap();
+        write(&path, "new");
+
+        let backup = name(&list_backups(&path).unwrap()[0]);
+        let timestamp = &backup["splits.lss.".len()..backup.len() - ".bak".len()];
+        assert_eq!(name(&restore_backup(&path, timestamp, 5).unwrap()), backup);
+        assert_eq!(read(&path), "old");
+
+        /* The version it replaced is backed up in turn */
+        let backups = list_backups(&path).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(read(&backups[1]), "new");
+
+        restore_backup(&path, &name(&backups[1]), 5).unwrap();
+        assert_eq!(read(&path), "new");
+
+        assert!(restore_backup(&path, "19700101-000000.000", 5).is_err());
+
+        fs::remove_dir_all(parent_dir(&path)).unwrap();
+    }
+}
@@ -0,0 +1,332 @@
+use livesplit_core::{SharedTimer, Timer, TimerPhase, TimeSpan, TimingMethod};
+use livesplit_core::analysis::current_pace;
+use livesplit_core::comparison::best_segments;
+use run_file::RunFile;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixListener;
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use time_format::{format_time, parse_time};
+use {reset, Event};
+
+/* Commands of the LiveSplit Server protocol, one per line */
+pub enum Command {
+    StartTimer,
+    StartOrSplit,
+    Split,
+    Unsplit,
+    SkipSplit,
+    Pause,
+    Resume,
+    Reset,
+    InitGameTime,
+    SetGameTime(TimeSpan),
+    SetLoadingTimes(TimeSpan),
+    PauseGameTime,
+    UnpauseGameTime,
+    SetComparison(String),
+    SwitchTo(TimingMethod),
+    GetDelta(Option<String>),
+    GetLastSplitTime,
+    GetComparisonSplitTime,
+    GetCurrentTime,
+    GetFinalTime(Option<String>),
+    GetBestPossibleTime,
+    GetPredictedTime(Option<String>),
+    GetSplitIndex,
+    GetCurrentSplitName,
+    GetPreviousSplitName,
+    GetCurrentTimerPhase,
+}
+
+/* A command from a client, with somewhere to send the response */
+pub struct Request {
+    pub command: Command,
+    pub reply: Sender<Option<String>>,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let line = line.trim();
+        let (name, argument) = match line.find(' ') {
+            Some(space) => (&line[..space], Some(line[space + 1..].trim())),
+            None => (line, None),
+        };
+        let time = || {
+            argument.and_then(parse_time)
+                .ok_or(format!("{} needs a time such as 1:23.45", name))
+        };
+
+        Ok(match name {
+            "starttimer" => Command::StartTimer,
+            "startorsplit" => Command::StartOrSplit,
+            "split" => Command::Split,
+            "unsplit" => Command::Unsplit,
+            "skipsplit" => Command::SkipSplit,
+            "pause" => Command::Pause,
+            "resume" => Command::Resume,
+            "reset" => Command::Reset,
+            "initgametime" => Command::InitGameTime,
+            "setgametime" => Command::SetGameTime(time()?),
+            "setloadingtimes" => Command::SetLoadingTimes(time()?),
+            "pausegametime" => Command::PauseGameTime,
+            "unpausegametime" => Command::UnpauseGameTime,
+            "setcomparison" => {
+                Command::SetComparison(argument.ok_or(String::from("setcomparison needs a comparison"))?
+                                           .to_string())
+            }
+            "switchto" => {
+                Command::SwitchTo(match argument {
+                    Some("realtime") => TimingMethod::RealTime,
+                    Some("gametime") => TimingMethod::GameTime,
+                    _ => return Err(String::from("switchto needs realtime or gametime")),
+                })
+            }
+            "getdelta" => Command::GetDelta(argument.map(String::from)),
+            "getlastsplittime" => Command::GetLastSplitTime,
+            "getcomparisonsplittime" => Command::GetComparisonSplitTime,
+            "getcurrenttime" => Command::GetCurrentTime,
+            "getfinaltime" => Command::GetFinalTime(argument.map(String::from)),
+            "getbestpossibletime" => Command::GetBestPossibleTime,
+            "getpredictedtime" => Command::GetPredictedTime(argument.map(String::from)),
+            "getsplitindex" => Command::GetSplitIndex,
+            "getcurrentsplitname" => Command::GetCurrentSplitName,
+            "getprevioussplitname" => Command::GetPreviousSplitName,
+            "getcurrenttimerphase" => Command::GetCurrentTimerPhase,
+            _ => return Err(format!("Unknown command {}", name)),
+        })
+    }
+
+    /* Carry out the command, returning the response for queries */
+    pub fn execute(self, timer: &SharedTimer, run_file: &mut RunFile) -> Option<String> {
+        match self {
+            Command::StartTimer => timer.write().start(),
+            Command::StartOrSplit => timer.write().split_or_start(),
+            Command::Split => timer.write().split(),
+            Command::Unsplit => timer.write().undo_split(),
+            Command::SkipSplit => timer.write().skip_split(),
+            Command::Pause => timer.write().pause(),
+            Command::Resume => timer.write().resume(),
+            Command::Reset => {
+                /* Practice attempts are only kept when chosen on screen */
+                let update_splits = !run_file.is_practicing();
+                reset(timer, run_file, update_splits);
+                return None;
+            }
+            Command::InitGameTime => timer.write().initialize_game_time(),
+            Command::SetGameTime(time) => timer.write().set_game_time(time),
+            Command::SetLoadingTimes(time) => timer.write().set_loading_times(time),
+            Command::PauseGameTime => timer.write().pause_game_time(),
+            Command::UnpauseGameTime => timer.write().resume_game_time(),
+            Command::SetComparison(name) => {
+                let _ = timer.write().set_current_comparison(name);
+                return None;
+            }
+            Command::SwitchTo(method) => {
+                timer.write().set_current_timing_method(method);
+                return None;
+            }
+            query => return Some(query.answer(&timer.read())),
+        }
+
+        run_file.mark_modified();
+        None
+    }
+
+    fn answer(self, timer: &Timer) -> String {
+        let method = timer.current_timing_method();
+        let index = timer.current_split_index();
+        let segment = |i: isize| if i >= 0 { timer.run().segments().get(i as usize) } else { None };
+
+        match self {
+            Command::GetDelta(comparison) => {
+                let comparison = comparison.unwrap_or(timer.current_comparison().to_string());
+                let delta = segment(index - 1).and_then(|s| {
+                    match (s.split_time()[method], s.comparison(&comparison)[method]) {
+                        (Some(split), Some(compared)) => Some(split - compared),
+                        _ => None,
+                    }
+                });
+                format_time(delta)
+            }
+            Command::GetLastSplitTime => format_time(segment(index - 1).and_then(|s| s.split_time()[method])),
+            Command::GetComparisonSplitTime => {
+                format_time(segment(index).and_then(|s| s.comparison(timer.current_comparison())[method]))
+            }
+            Command::GetCurrentTime => format_time(timer.current_time()[method]),
+            Command::GetFinalTime(comparison) => {
+                let comparison = comparison.unwrap_or(timer.current_comparison().to_string());
+                format_time(timer.run().segments().last().and_then(|s| s.comparison(&comparison)[method]))
+            }
+            /* The final time if every remaining segment is a gold */
+            Command::GetBestPossibleTime => format_time(current_pace::calculate(timer, best_segments::NAME)),
+            Command::GetPredictedTime(comparison) => {
+                let comparison = comparison.unwrap_or(timer.current_comparison().to_string());
+                format_time(current_pace::calculate(timer, &comparison))
+            }
+            Command::GetSplitIndex => index.to_string(),
+            Command::GetCurrentSplitName => segment(index).map(|s| s.name().to_string()).unwrap_or_default(),
+            Command::GetPreviousSplitName => segment(index - 1).map(|s| s.name().to_string()).unwrap_or_default(),
+            Command::GetCurrentTimerPhase => {
+                String::from(match timer.current_phase() {
+                    TimerPhase::NotRunning => "NotRunning",
+                    TimerPhase::Running => "Running",
+                    TimerPhase::Paused => "Paused",
+                    TimerPhase::Ended => "Ended",
+                })
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+/* Accept clients on a TCP address such as 127.0.0.1:16834 */
+pub fn listen_tcp(address: &str, events: Sender<Event>) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    thread::spawn(move || for stream in listener.incoming() {
+        if let Ok(stream) = stream {
+            let events = events.clone();
+            if let Ok(writer) = stream.try_clone() {
+                thread::spawn(move || serve(BufReader::new(stream), writer, events));
+            }
+        }
+    });
+    Ok(())
+}
+
+/* Accept clients on a Unix socket, replacing any stale socket file. Anything
+ * else at the path is left alone. */
+pub fn listen_unix(path: &str, events: Sender<Event>) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(ref metadata) if metadata.file_type().is_socket() => fs::remove_file(path)?,
+        Ok(_) => return Err(io::Error::new(io::ErrorKind::AlreadyExists, "the file exists and is not a socket")),
+        Err(ref error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => return Err(error),
+    }
+    let listener = UnixListener::bind(path)?;
+    thread::spawn(move || for stream in listener.incoming() {
+        if let Ok(stream) = stream {
+            let events = events.clone();
+            if let Ok(writer) = stream.try_clone() {
+                thread::spawn(move || serve(BufReader::new(stream), writer, events));
+            }
+        }
+    });
+    Ok(())
+}
+
+/* Run each line from a client as a command, replying to queries. Like LiveSplit
+ * Server, nothing else is ever answered, not even commands that can't be parsed,
+ * so a client's queries and answers never get out of step. */
+pub fn serve<R: BufRead, W: Write>(reader: R, mut writer: W, events: Sender<Event>) {
+    serve_with(reader,
+               |response| write!(writer, "{}\r\n", response).and_then(|_| writer.flush()),
+               events);
+}
+
+/* Like serve, but hands responses to `respond` instead of writing them out */
+pub fn serve_with<R, F>(reader: R, mut respond: F, events: Sender<Event>)
+    where R: BufRead,
+          F: FnMut(&str) -> io::Result<()>
+{
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match Command::parse(&line) {
+            Ok(command) => {
+                let (reply, response) = channel();
+                let request = Request {
+                    command: command,
+                    reply: reply,
+                };
+                if events.send(Event::Command(request)).is_err() {
+                    break;
+                }
+                match response.recv() {
+                    Ok(response) => response,
+                    Err(_) => break,
+                }
+            }
+            Err(_) => None,
+        };
+
+        if let Some(response) = response {
+            if respond(&response).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn parses_commands_with_arguments() {
+        match Command::parse("setgametime 1:23.5\r\n") {
+            Ok(Command::SetGameTime(time)) => assert_eq!(time.total_seconds(), 83.5),
+            _ => panic!("expected setgametime"),
+        }
+        match Command::parse("setcomparison Best Segments") {
+            Ok(Command::SetComparison(ref name)) => assert_eq!(name, "Best Segments"),
+            _ => panic!("expected setcomparison"),
+        }
+        match Command::parse("switchto gametime") {
+            Ok(Command::SwitchTo(TimingMethod::GameTime)) => {}
+            _ => panic!("expected switchto"),
+        }
+        match Command::parse("getdelta") {
+            Ok(Command::GetDelta(None)) => {}
+            _ => panic!("expected getdelta"),
+        }
+        match Command::parse("getpredictedtime Personal Best") {
+            Ok(Command::GetPredictedTime(Some(ref name))) => assert_eq!(name, "Personal Best"),
+            _ => panic!("expected getpredictedtime"),
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_commands_and_missing_arguments() {
+        assert!(Command::parse("setsplitname 1 Foo").is_err());
+        assert!(Command::parse("setgametime").is_err());
+        assert!(Command::parse("setgametime soon").is_err());
+        assert!(Command::parse("setcomparison").is_err());
+        assert!(Command::parse("switchto sometime").is_err());
+    }
+
+    #[test]
+    fn only_answers_queries() {
+        let (events, receiver) = channel();
+        thread::spawn(move || for event in receiver {
+            if let Event::Command(request) = event {
+                let response = match request.command {
+                    Command::GetSplitIndex => Some(String::from("0")),
+                    _ => None,
+                };
+                let _ = request.reply.send(response);
+            }
+        });
+
+        let mut responses = Vec::new();
+        let input = Cursor::new("alwayspausegametime\nstarttimer\n\ngetsplitindex\n");
+        serve_with(input,
+                   |response| {
+                       responses.push(response.to_string());
+                       Ok(())
+                   },
+                   events);
+        assert_eq!(responses, ["0"]);
+    }
+}
@@ -0,0 +1,376 @@
+use chrono::{DateTime, Utc};
+use livesplit_core::{Run, TimeSpan, TimingMethod};
+use livesplit_core::analysis::sum_of_segments;
+use std::cmp::min;
+
+/* What the segment history says about one segment */
+pub struct SegmentStats {
+    pub name: String,
+    /* Attempt ids and segment times, in the order they're stored */
+    pub times: Vec<(i32, TimeSpan)>,
+    pub best: Option<TimeSpan>,
+    pub average: Option<TimeSpan>,
+    pub median: Option<TimeSpan>,
+    pub std_dev: Option<TimeSpan>,
+}
+
+impl SegmentStats {
+    pub fn count(&self) -> usize {
+        self.times.len()
+    }
+}
+
+/* Stats for every segment of the run. Attempts that skipped a segment have
+ * no time for it and are left out. */
+pub fn segment_stats(run: &Run, method: TimingMethod) -> Vec<SegmentStats> {
+    run.segments()
+        .iter()
+        .map(|segment| {
+            let times = segment.segment_history()
+                .iter()
+                .filter_map(|&(id, ref time)| time[method].map(|t| (id, t)))
+                .collect::<Vec<_>>();
+            let mut seconds = times.iter().map(|&(_, t)| t.total_seconds()).collect::<Vec<_>>();
+            seconds.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+            let best = segment.best_segment_time()[method].or(seconds.first().map(|&s| TimeSpan::from_seconds(s)));
+            SegmentStats {
+                name: segment.name().to_string(),
+                times: times,
+                best: best,
+                average: mean(&seconds).map(TimeSpan::from_seconds),
+                median: median(&seconds).map(TimeSpan::from_seconds),
+                std_dev: std_dev(&seconds).map(TimeSpan::from_seconds),
+            }
+        })
+        .collect()
+}
+
+pub fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+/* `values` must be sorted */
+pub fn median(values: &[f64]) -> Option<f64> {
+    let count = values.len();
+    if count == 0 {
+        None
+    } else if count % 2 == 1 {
+        Some(values[count / 2])
+    } else {
+        Some((values[count / 2 - 1] + values[count / 2]) / 2.0)
+    }
+}
+
+pub fn std_dev(values: &[f64]) -> Option<f64> {
+    mean(values).map(|mean| {
+        let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / values.len() as f64;
+        variance.sqrt()
+    })
+}
+
+/* One attempt from the run's attempt history */
+pub struct AttemptInfo {
+    pub index: i32,
+    pub started: Option<DateTime<Utc>>,
+    pub ended: Option<DateTime<Utc>>,
+    /* Final time, if the run was finished */
+    pub time: Option<TimeSpan>,
+    pub pause_time: Option<TimeSpan>,
+    /* Index of the segment the attempt was reset in */
+    pub reset_segment: Option<usize>,
+}
+
+/* How often attempts ended in a segment */
+pub struct SegmentResets {
+    pub name: String,
+    /* Attempts that made it to the segment */
+    pub reached: usize,
+    pub resets: usize,
+}
+
+pub struct AttemptStats {
+    pub attempts: Vec<AttemptInfo>,
+    pub finished: usize,
+    pub resets: Vec<SegmentResets>,
+    pub playtime: TimeSpan,
+    /* Indices into `attempts` of each attempt that beat every one before it */
+    pub pb_progression: Vec<usize>,
+}
+
+impl AttemptStats {
+    /* Share of attempts that were finished, from 0 to 1 */
+    pub fn completion_rate(&self) -> Option<f64> {
+        if self.attempts.is_empty() {
+            None
+        } else {
+            Some(self.finished as f64 / self.attempts.len() as f64)
+        }
+    }
+}
+
+impl SegmentResets {
+    /* Share of attempts reaching the segment that were reset in it */
+    pub fn rate(&self) -> Option<f64> {
+        if self.reached == 0 {
+            None
+        } else {
+            Some(self.resets as f64 / self.reached as f64)
+        }
+    }
+}
+
+/* Go through the attempt history, working out where each attempt ended from
+ * the segment history */
+pub fn attempt_stats(run: &Run, method: TimingMethod) -> AttemptStats {
+    let segments = run.segments();
+    let mut resets = segments.iter()
+        .map(|s| {
+            SegmentResets {
+                name: s.name().to_string(),
+                reached: 0,
+                resets: 0,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut attempts = Vec::new();
+    let mut playtime = 0.0;
+    let mut pb_progression = Vec::new();
+    let mut best: Option<f64> = None;
+
+    for attempt in run.attempt_history() {
+        let index = attempt.index();
+        let time = attempt.time()[method];
+        let started = attempt.started().map(|t| t.time);
+        let ended = attempt.ended().map(|t| t.time);
+
+        /* Segments are only in an attempt's history up to where it ended */
+        let reached = segments.iter()
+            .take_while(|s| s.segment_history().iter().any(|&(id, _)| id == index))
+            .count();
+        let reset_segment = if time.is_some() || segments.is_empty() {
+            None
+        } else {
+            Some(min(reached, segments.len() - 1))
+        };
+        for segment in resets.iter_mut().take(reset_segment.map(|i| i + 1).unwrap_or(segments.len())) {
+            segment.reached += 1;
+        }
+        if let Some(segment) = reset_segment {
+            resets[segment].resets += 1;
+        }
+
+        let attempt_playtime = match (started, ended) {
+            (Some(started), Some(ended)) => {
+                Some(TimeSpan::from_seconds(ended.signed_duration_since(started).num_milliseconds() as f64 / 1000.0))
+            }
+            _ => {
+                attempt.time().real_time.map(|t| {
+                    t + attempt.pause_time().unwrap_or(TimeSpan::zero())
+                })
+            }
+        };
+        playtime += attempt_playtime.map(|t| t.total_seconds()).unwrap_or(0.0);
+
+        if let Some(time) = time {
+            if best.map(|best| time.total_seconds() < best).unwrap_or(true) {
+                best = Some(time.total_seconds());
+                pb_progression.push(attempts.len());
+            }
+        }
+
+        attempts.push(AttemptInfo {
+            index: index,
+            started: started,
+            ended: ended,
+            time: time,
+            pause_time: attempt.pause_time(),
+            reset_segment: reset_segment,
+        });
+    }
+
+    AttemptStats {
+        finished: attempts.iter().filter(|a| a.time.is_some()).count(),
+        attempts: attempts,
+        resets: resets,
+        playtime: TimeSpan::from_seconds(playtime),
+        pb_progression: pb_progression,
+    }
+}
+
+/* Segment times of every attempt in the attempt history, by attempt and then
+ * segment, None where the attempt has no time for a segment */
+pub fn attempt_segment_times(run: &Run, method: TimingMethod) -> Vec<Vec<Option<TimeSpan>>> {
+    run.attempt_history()
+        .iter()
+        .map(|attempt| {
+            run.segments()
+                .iter()
+                .map(|segment| {
+                    segment.segment_history()
+                        .iter()
+                        .find(|&&(id, _)| id == attempt.index())
+                        .and_then(|&(_, ref time)| time[method])
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/* Which of the times from attempt_segment_times were golds, beating the best
+ * segment known at the time. Best segments from before the attempt history,
+ * kept in the segment history without an attempt or only as the run's best
+ * segment, are known from the start. A segment's first time is not a gold. */
+pub fn golds(run: &Run, method: TimingMethod, times: &[Vec<Option<TimeSpan>>]) -> Vec<Vec<bool>> {
+    let attempts = run.attempt_history().iter().map(|a| a.index()).collect::<Vec<_>>();
+    let mut best = run.segments()
+        .iter()
+        .enumerate()
+        .map(|(s, segment)| {
+            let earlier = segment.segment_history()
+                .iter()
+                .filter(|&&(id, _)| !attempts.contains(&id))
+                .filter_map(|&(_, ref time)| time[method])
+                .map(|t| t.total_seconds());
+            /* A best segment no attempt got down to must be older than them */
+            let fastest = min_seconds(times.iter().filter_map(|attempt| attempt[s]).map(|t| t.total_seconds()));
+            let stored = segment.best_segment_time()[method]
+                .map(|t| t.total_seconds())
+                .and_then(|stored| if fastest.map(|f| stored < f).unwrap_or(true) { Some(stored) } else { None });
+            min_seconds(earlier.chain(stored))
+        })
+        .collect::<Vec<_>>();
+
+    times.iter()
+        .map(|attempt| {
+            attempt.iter()
+                .zip(best.iter_mut())
+                .map(|(time, best)| {
+                    let seconds = match *time {
+                        Some(time) => time.total_seconds(),
+                        None => return false,
+                    };
+                    let gold = best.map(|b| seconds < b).unwrap_or(false);
+                    if best.map(|b| seconds < b).unwrap_or(true) {
+                        *best = Some(seconds);
+                    }
+                    gold
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn min_seconds<I: Iterator<Item = f64>>(seconds: I) -> Option<f64> {
+    seconds.fold(None, |min, s| Some(min.map(|m: f64| m.min(s)).unwrap_or(s)))
+}
+
+/* Worked out the way livesplit-core's sum of best component does, so the two
+ * always agree */
+pub fn sum_of_best(run: &Run, method: TimingMethod) -> Option<TimeSpan> {
+    sum_of_segments::calculate_best(run.segments(), false, true, method)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use livesplit_core::{Segment, Time};
+
+    fn time(seconds: f64) -> Time {
+        Time::new().with_real_time(Some(TimeSpan::from_seconds(seconds)))
+    }
+
+    /* A run with segments A, B and C and an attempt for each list of segment
+     * times, None being a skipped segment. Attempts with fewer times than
+     * segments were reset in the segment after their last one. */
+    fn run(attempts: &[&[Option<f64>]]) -> Run {
+        let mut run = Run::new();
+        for name in &["A", "B", "C"] {
+            run.push_segment(Segment::new(*name));
+        }
+        for (i, times) in attempts.iter().enumerate() {
+            let index = i as i32 + 1;
+            for (s, seconds) in times.iter().enumerate() {
+                let segment_time = seconds.map(time).unwrap_or_default();
+                run.segments_mut()[s].segment_history_mut().insert(index, segment_time);
+            }
+            let total = if times.len() == 3 && times.iter().all(|t| t.is_some()) {
+                time(times.iter().map(|t| t.unwrap()).sum())
+            } else {
+                Time::default()
+            };
+            run.add_attempt(total, None, None, None);
+        }
+        run
+    }
+
+    fn reached(stats: &AttemptStats) -> Vec<usize> {
+        stats.resets.iter().map(|r| r.reached).collect()
+    }
+
+    fn resets(stats: &AttemptStats) -> Vec<usize> {
+        stats.resets.iter().map(|r| r.resets).collect()
+    }
+
+    #[test]
+    fn finished_attempt() {
+        let stats = attempt_stats(&run(&[&[Some(10.0), Some(20.0), Some(30.0)]]), TimingMethod::RealTime);
+        assert_eq!(stats.finished, 1);
+        assert_eq!(stats.attempts[0].reset_segment, None);
+        assert_eq!(stats.attempts[0].time.map(|t| t.total_seconds()), Some(60.0));
+        assert_eq!(stats.completion_rate(), Some(1.0));
+        assert_eq!(reached(&stats), vec![1, 1, 1]);
+        assert_eq!(resets(&stats), vec![0, 0, 0]);
+        assert_eq!(stats.pb_progression, vec![0]);
+    }
+
+    #[test]
+    fn reset_in_first_segment() {
+        let stats = attempt_stats(&run(&[&[]]), TimingMethod::RealTime);
+        assert_eq!(stats.finished, 0);
+        assert_eq!(stats.attempts[0].reset_segment, Some(0));
+        assert_eq!(stats.completion_rate(), Some(0.0));
+        assert_eq!(reached(&stats), vec![1, 0, 0]);
+        assert_eq!(resets(&stats), vec![1, 0, 0]);
+        assert!(stats.pb_progression.is_empty());
+    }
+
+    #[test]
+    fn skipped_segment_counts_as_reached() {
+        let stats = attempt_stats(&run(&[&[Some(10.0), None]]), TimingMethod::RealTime);
+        assert_eq!(stats.attempts[0].reset_segment, Some(2));
+        assert_eq!(reached(&stats), vec![1, 1, 1]);
+        assert_eq!(resets(&stats), vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn pb_progression_skips_slower_and_reset_attempts() {
+        let run = run(&[&[Some(10.0), Some(20.0), Some(30.0)],
+                        &[Some(9.0)],
+                        &[Some(11.0), Some(21.0), Some(31.0)],
+                        &[Some(9.0), Some(19.0), Some(29.0)]]);
+        let stats = attempt_stats(&run, TimingMethod::RealTime);
+        assert_eq!(stats.finished, 3);
+        assert_eq!(stats.pb_progression, vec![0, 3]);
+        assert_eq!(resets(&stats), vec![0, 1, 0]);
+        assert_eq!(reached(&stats), vec![4, 4, 3]);
+    }
+
+    #[test]
+    fn golds_need_an_earlier_best() {
+        let mut run = run(&[&[Some(10.0), Some(20.0), Some(30.0)], &[Some(9.0), Some(25.0), Some(28.0)]]);
+        /* A's best segment is older than the attempt history */
+        run.segments_mut()[0].set_best_segment_time(time(5.0));
+        run.segments_mut()[1].set_best_segment_time(time(20.0));
+        run.segments_mut()[2].set_best_segment_time(time(28.0));
+
+        let times = attempt_segment_times(&run, TimingMethod::RealTime);
+        assert_eq!(golds(&run, TimingMethod::RealTime, &times),
+                   vec![vec![false, false, false], vec![false, false, true]]);
+    }
+}
@@ -0,0 +1,86 @@
+use livesplit_core::TimeSpan;
+
+/* Format a time as [-][h:]mm:ss.ff, or "-" if there is none */
+pub fn format_time(time: Option<TimeSpan>) -> String {
+    let time = match time {
+        Some(time) => time,
+        None => return String::from("-"),
+    };
+
+    let total = time.total_seconds();
+    let sign = if total < 0.0 { "-" } else { "" };
+    let hundredths = (total.abs() * 100.0).floor() as u64;
+    let (hours, minutes) = (hundredths / 360000, hundredths / 6000 % 60);
+    let (seconds, fraction) = (hundredths / 100 % 60, hundredths % 100);
+
+    if hours > 0 {
+        format!("{}{}:{:02}:{:02}.{:02}", sign, hours, minutes, seconds, fraction)
+    } else {
+        format!("{}{}:{:02}.{:02}", sign, minutes, seconds, fraction)
+    }
+}
+
+/* Parse times such as "1:23:45.67", "23:45" or "45.6", optionally negative */
+pub fn parse_time(text: &str) -> Option<TimeSpan> {
+    let text = text.trim();
+    let (sign, text) = if text.starts_with('-') {
+        (-1.0, &text[1..])
+    } else {
+        (1.0, text)
+    };
+    if text.is_empty() {
+        return None;
+    }
+
+    let mut seconds = 0.0;
+    for (i, part) in text.split(':').enumerate() {
+        if i > 2 {
+            return None;
+        }
+        let value: f64 = part.parse().ok()?;
+        if value < 0.0 {
+            return None;
+        }
+        seconds = seconds * 60.0 + value;
+    }
+
+    Some(TimeSpan::from_seconds(sign * seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(text: &str) -> Option<f64> {
+        parse_time(text).map(|t| (t.total_seconds() * 100.0).round() / 100.0)
+    }
+
+    #[test]
+    fn parses_hours_minutes_and_seconds() {
+        assert_eq!(seconds("1:23:45.67"), Some(5025.67));
+        assert_eq!(seconds("23:45"), Some(1425.0));
+        assert_eq!(seconds("45.6"), Some(45.6));
+        assert_eq!(seconds(" 0:05 "), Some(5.0));
+    }
+
+    #[test]
+    fn parses_negative_times() {
+        assert_eq!(seconds("-1:30"), Some(-90.0));
+    }
+
+    #[test]
+    fn rejects_invalid_times() {
+        assert_eq!(seconds(""), None);
+        assert_eq!(seconds("-"), None);
+        assert_eq!(seconds("1:2:3:4"), None);
+        assert_eq!(seconds("1:-2"), None);
+        assert_eq!(seconds("abc"), None);
+    }
+
+    #[test]
+    fn formats_what_it_parses() {
+        assert_eq!(format_time(parse_time("1:23:45.50")), "1:23:45.50");
+        assert_eq!(format_time(parse_time("-5.5")), "-0:05.50");
+        assert_eq!(format_time(None), "-");
+    }
+}